@@ -1,3 +1,4 @@
+use rust_http_server::middleware::{BearerAuth, Cors, Principal, RequestIds};
 use rust_http_server::{Middleware, NextFn, Request, Response, Router, Server};
 use std::sync::Arc;
 use std::time::Instant;
@@ -7,7 +8,12 @@ struct RequestLogger;
 impl Middleware for RequestLogger {
     fn handle(&self, req: Request, next: NextFn) -> Response {
         let start = Instant::now();
-        println!("[Middleware] Incoming: {} {}", req.method, req.path);
+        println!(
+            "[Middleware] Incoming: {} {} ({})",
+            req.method,
+            req.path,
+            req.request_id().unwrap_or("-")
+        );
 
         let response = next(req);
 
@@ -20,32 +26,13 @@ impl Middleware for RequestLogger {
     }
 }
 
-// basic auth middleware
-struct AuthMiddleware {
-    secret_token: String,
-}
-impl Middleware for AuthMiddleware {
-    fn handle(&self, req: Request, next: NextFn) -> Response {
-        if let Some(auth_header) = req
-            .headers
-            .iter()
-            .find(|(k, _)| k.eq_ignore_ascii_case("Authorization"))
-        {
-            if auth_header.1 == format!("Bearer {}", self.secret_token) {
-                println!("[AuthMiddleware] Authenticated!");
-                return next(req);
-            }
-        }
-        println!("[AuthMiddleware] Authentication failed!");
-        Response::new(401).with_body("Unauthorized")
-    }
-}
-
 #[tokio::main]
 async fn main() {
     let mut router = Router::new();
 
+    router.use_global(RequestIds::uuid());
     router.use_global(RequestLogger);
+    router.use_global(Cors::new().allow_origin("http://localhost:3000"));
 
     router.get("/", |_req: Request| {
         Response::new(200).with_body("Hello from GET /!")
@@ -62,12 +49,13 @@ async fn main() {
     });
 
     // GET handler with route-specific middleware
-    let auth_mw = Arc::new(AuthMiddleware {
-        secret_token: "supersecret".to_string(),
-    });
+    let auth_mw = Arc::new(BearerAuth::with_tokens(&[("supersecret", "admin")]).realm("example"));
     router.get_with_middlewares(
         "/secure",
-        |_req: Request| Response::new(200).with_body("This is a secure area!"),
+        |req: Request| {
+            let user = req.extension::<Principal>().map(|p| p.name.clone()).unwrap_or_default();
+            Response::new(200).with_body(&format!("This is a secure area, {}!", user))
+        },
         vec![auth_mw.clone()],
     );
 
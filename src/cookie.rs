@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::time::Duration;
+
+use crate::header::is_valid_name;
+
+#[cfg(feature = "secure-cookies")]
+mod secure;
+
+#[cfg(feature = "secure-cookies")]
+pub use secure::CookieKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    // Sent on cross-site requests too; browsers require `Secure`, which is then always set.
+    None,
+}
+
+// A cookie to send in `Set-Cookie`. Attributes are set with consuming builder methods:
+//
+//     let cookie = Cookie::new("theme", "dark")
+//         .path("/")
+//         .max_age(Duration::from_secs(86400))
+//         .http_only(true)
+//         .same_site(SameSite::Lax);
+//     Response::new(200).with_cookie(cookie)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    expires: Option<DateTime<Utc>>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            expires: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    // A cookie that makes the browser delete `name`. Path and Domain must match the
+    // ones the cookie was set with.
+    pub fn removal(name: &str) -> Self {
+        Self::new(name, "")
+            .max_age(Duration::ZERO)
+            .expires(DateTime::UNIX_EPOCH)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn expires(mut self, expires: DateTime<Utc>) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    #[cfg(feature = "secure-cookies")]
+    pub(crate) fn with_value(mut self, value: String) -> Self {
+        self.value = value;
+        self
+    }
+
+    // Whether the cookie can be serialized without corrupting `Set-Cookie`: the name must
+    // be a token, the value cookie-octets (RFC 6265), and Path/Domain free of `;`.
+    pub fn is_valid(&self) -> bool {
+        let attribute_ok = |value: &Option<String>| {
+            value
+                .as_deref()
+                .is_none_or(|v| v.bytes().all(|b| (0x20..0x7f).contains(&b) && b != b';'))
+        };
+        is_valid_name(&self.name)
+            && is_valid_value(&self.value)
+            && attribute_ok(&self.path)
+            && attribute_ok(&self.domain)
+    }
+}
+
+// The `Set-Cookie` header value.
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(expires) = &self.expires {
+            write!(f, "; Expires={}", expires.format("%a, %d %b %Y %H:%M:%S GMT"))?;
+        }
+        if let Some(max_age) = &self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict")?,
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax")?,
+            Some(SameSite::None) => write!(f, "; SameSite=None")?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+// cookie-value = *cookie-octet / ( DQUOTE *cookie-octet DQUOTE )
+fn is_valid_value(value: &str) -> bool {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    value.bytes().all(|b| {
+        matches!(b, 0x21 | 0x23..=0x2b | 0x2d..=0x3a | 0x3c..=0x5b | 0x5d..=0x7e)
+    })
+}
@@ -0,0 +1,134 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+
+use crate::cookie::Cookie;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const NONCE_LEN: usize = 12;
+
+// Server secret for signed and encrypted ("private") cookies. Signed cookies are readable
+// by the client but cannot be altered; private cookies can be neither read nor altered.
+// The cookie name is bound into the MAC/ciphertext, so values cannot be moved between
+// cookies.
+//
+//     let key = CookieKey::from_secret(&secret);
+//     let response = Response::new(200).with_cookie(key.sign(Cookie::new("user", "42")));
+//     let user = req.signed_cookie("user", &key);
+#[derive(Clone)]
+pub struct CookieKey {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+impl CookieKey {
+    // Derives the signing and encryption keys from `secret`, which must be at least 32
+    // bytes of random data. Panics on a shorter secret.
+    pub fn from_secret(secret: &[u8]) -> Self {
+        if secret.len() < 32 {
+            panic!("CookieKey secret must be at least 32 bytes, got {}", secret.len());
+        }
+        Self {
+            signing: derive(secret, b"cookie-signing"),
+            encryption: derive(secret, b"cookie-encryption"),
+        }
+    }
+
+    // A random key. Cookies issued with it do not survive a restart.
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret).expect("OS random number generator unavailable");
+        Self::from_secret(&secret)
+    }
+
+    pub fn sign(&self, cookie: Cookie) -> Cookie {
+        let tag = self.mac(cookie.name(), cookie.value()).finalize().into_bytes();
+        let value = format!("{}.{}", cookie.value(), URL_SAFE_NO_PAD.encode(tag));
+        cookie.with_value(value)
+    }
+
+    // The original value of a cookie produced by `sign`, if the signature checks out.
+    pub fn verify(&self, name: &str, signed_value: &str) -> Option<String> {
+        let (value, tag) = signed_value.rsplit_once('.')?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+        self.mac(name, value).verify_slice(&tag).ok()?;
+        Some(value.to_string())
+    }
+
+    // Encrypts the cookie's value; the result is URL-safe base64, so the plaintext may
+    // contain characters that are not allowed in cookies.
+    pub fn encrypt(&self, cookie: Cookie) -> Cookie {
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).expect("OS random number generator unavailable");
+        let payload = Payload {
+            msg: cookie.value().as_bytes(),
+            aad: cookie.name().as_bytes(),
+        };
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .expect("AES-GCM encryption failed");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        cookie.with_value(URL_SAFE_NO_PAD.encode(sealed))
+    }
+
+    // The plaintext of a cookie produced by `encrypt`, if it decrypts and authenticates.
+    pub fn decrypt(&self, name: &str, sealed_value: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(sealed_value).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: name.as_bytes(),
+        };
+        let plaintext = self.cipher().decrypt(Nonce::from_slice(nonce), payload).ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    // A MAC over `value` for tokens that are not cookie values by themselves. `context`
+    // plays the part of the cookie name and keeps tags from being reused elsewhere.
+    pub(crate) fn tag(&self, context: &str, value: &str) -> String {
+        URL_SAFE_NO_PAD.encode(self.mac(context, value).finalize().into_bytes())
+    }
+
+    pub(crate) fn verify_tag(&self, context: &str, value: &str, tag: &str) -> bool {
+        match URL_SAFE_NO_PAD.decode(tag) {
+            Ok(tag) => self.mac(context, value).verify_slice(&tag).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn mac(&self, name: &str, value: &str) -> HmacSha256 {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.signing).expect("HMAC accepts any key length");
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new_from_slice(&self.encryption).expect("32-byte AES-256 key")
+    }
+}
+
+// Never print key material.
+impl fmt::Debug for CookieKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CookieKey(..)")
+    }
+}
+
+fn derive(secret: &[u8], label: &[u8]) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(label);
+    mac.finalize().into_bytes().into()
+}
@@ -0,0 +1,379 @@
+// Typed handler arguments. Wrap a function whose arguments implement `FromRequest` with
+// `extract`, and each argument is pulled out of the request before the call; the first
+// one that fails answers the request with its rejection instead.
+//
+//     #[derive(Deserialize)]
+//     struct Filter { active: Option<bool> }
+//
+//     router.state(App::new());
+//     router.get("/teams/{id}/users", extract(|Path(id): Path<u64>, Query(filter): Query<Filter>, State(app): State<App>| {
+//         Json(app.users(id, filter.active))
+//     }));
+
+use std::convert::Infallible;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::form::FormError;
+use crate::handler::Handler;
+use crate::header::HeaderMap;
+use crate::http_method::HttpMethod;
+use crate::multipart::MultipartError;
+use crate::request::ParamError;
+use crate::{Request, Response};
+
+#[cfg(feature = "serde")]
+use crate::json::JsonError;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+
+// A value that can be taken from a request. `Rejection` becomes the response when it can't.
+pub trait FromRequest: Sized {
+    type Rejection: Into<Response>;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Rejection>;
+}
+
+// What extracting handlers may return.
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+// Wraps a function taking extractors so it can be registered as a route handler.
+pub fn extract<F, Args>(f: F) -> Extract<F, Args>
+where
+    F: ExtractHandler<Args>,
+{
+    Extract {
+        f,
+        args: PhantomData,
+    }
+}
+
+pub struct Extract<F, Args> {
+    f: F,
+    args: PhantomData<fn() -> Args>,
+}
+
+impl<F, Args> Handler for Extract<F, Args>
+where
+    F: ExtractHandler<Args>,
+{
+    fn handle(&self, req: Request) -> Response {
+        self.f.call(req)
+    }
+}
+
+// Implemented for `Fn`s of up to eight extractor arguments; `Args` is the tuple of their
+// types.
+pub trait ExtractHandler<Args>: Send + Sync + 'static {
+    fn call(&self, req: Request) -> Response;
+}
+
+macro_rules! impl_extract_handler {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> ExtractHandler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: IntoResponse,
+            $($arg: FromRequest,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&self, req: Request) -> Response {
+                $(
+                    let $arg = match $arg::from_request(&req) {
+                        Ok(value) => value,
+                        Err(rejection) => return rejection.into(),
+                    };
+                )*
+                (self)($($arg),*).into_response()
+            }
+        }
+    };
+}
+
+impl_extract_handler!();
+impl_extract_handler!(A1);
+impl_extract_handler!(A1, A2);
+impl_extract_handler!(A1, A2, A3);
+impl_extract_handler!(A1, A2, A3, A4);
+impl_extract_handler!(A1, A2, A3, A4, A5);
+impl_extract_handler!(A1, A2, A3, A4, A5, A6);
+impl_extract_handler!(A1, A2, A3, A4, A5, A6, A7);
+impl_extract_handler!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+// Why an extractor without an error type of its own failed. Converts into a response
+// with `status` and a plain text body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    status: u16,
+    message: String,
+}
+
+impl Rejection {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+impl From<Rejection> for Response {
+    fn from(rejection: Rejection) -> Self {
+        let response = Response::new(rejection.status);
+        let body = format!("{} {}: {}", response.status_code, response.reason_phrase, rejection);
+        response.with_body(&body)
+    }
+}
+
+// For extractors that can't fail.
+impl From<Infallible> for Response {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+// Path parameters captured by the route pattern. A struct takes them by name; any other
+// type takes the route's only parameter. Values that don't parse give a 400.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned> FromRequest for Path<T> {
+    type Rejection = Rejection;
+
+    fn from_request(req: &Request) -> Result<Self, Rejection> {
+        crate::form::from_params(&req.params)
+            .map(Path)
+            .map_err(|e| Rejection::new(400, format!("invalid path parameter {}", e)))
+    }
+}
+
+// The query string, deserialized like `Request::query_as`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    type Rejection = FormError;
+
+    fn from_request(req: &Request) -> Result<Self, FormError> {
+        req.query_as().map(Query)
+    }
+}
+
+// A JSON body, read like `Request::json`. As a return value, a 200 JSON response.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    type Rejection = JsonError;
+
+    fn from_request(req: &Request) -> Result<Self, JsonError> {
+        req.json().map(Json)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        Response::json(&self.0)
+    }
+}
+
+// A URL-encoded form body, read like `Request::form_as`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Form<T>(pub T);
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned> FromRequest for Form<T> {
+    type Rejection = FormError;
+
+    fn from_request(req: &Request) -> Result<Self, FormError> {
+        req.form_as().map(Form)
+    }
+}
+
+// A value shared with `Router::state`. Missing state is a server misconfiguration and
+// gives a 500.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for State<T> {
+    type Rejection = Rejection;
+
+    fn from_request(req: &Request) -> Result<Self, Rejection> {
+        match req.extension::<T>() {
+            Some(value) => Ok(State(value.clone())),
+            None => Err(Rejection::new(
+                500,
+                format!("no state of type {}", std::any::type_name::<T>()),
+            )),
+        }
+    }
+}
+
+// A value a middleware attached to the request, such as the authenticated `Principal`.
+// Like `State`, a missing value gives a 500; take an `Option<Extension<T>>` where the
+// middleware may leave it out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for Extension<T> {
+    type Rejection = Rejection;
+
+    fn from_request(req: &Request) -> Result<Self, Rejection> {
+        match req.extension::<T>() {
+            Some(value) => Ok(Extension(value.clone())),
+            None => Err(Rejection::new(
+                500,
+                format!("no extension of type {}", std::any::type_name::<T>()),
+            )),
+        }
+    }
+}
+
+// `None` where `T` would be rejected.
+impl<T: FromRequest> FromRequest for Option<T> {
+    type Rejection = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Infallible> {
+        Ok(T::from_request(req).ok())
+    }
+}
+
+impl<T: FromRequest> FromRequest for Result<T, T::Rejection> {
+    type Rejection = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Infallible> {
+        Ok(T::from_request(req))
+    }
+}
+
+// The whole request, for anything the extractors don't cover.
+impl FromRequest for Request {
+    type Rejection = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Infallible> {
+        Ok(req.clone())
+    }
+}
+
+impl FromRequest for HeaderMap {
+    type Rejection = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Infallible> {
+        Ok(req.headers.clone())
+    }
+}
+
+impl FromRequest for HttpMethod {
+    type Rejection = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Infallible> {
+        Ok(req.method.clone())
+    }
+}
+
+// The raw body.
+impl FromRequest for Vec<u8> {
+    type Rejection = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Infallible> {
+        Ok(req.body.clone())
+    }
+}
+
+// The body as text; a body that isn't UTF-8 gives a 400.
+impl FromRequest for String {
+    type Rejection = Rejection;
+
+    fn from_request(req: &Request) -> Result<Self, Rejection> {
+        String::from_utf8(req.body.clone()).map_err(|_| Rejection::new(400, "request body is not valid UTF-8"))
+    }
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+// A 204 with no body.
+impl IntoResponse for () {
+    fn into_response(self) -> Response {
+        Response::new(204)
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Response {
+        Response::new(200).with_body(self)
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response {
+        Response::new(200).with_bytes(self.into_bytes())
+    }
+}
+
+// `T`'s response with `status` instead, e.g. `(201, Json(user))`.
+impl<T: IntoResponse> IntoResponse for (u16, T) {
+    fn into_response(self) -> Response {
+        let (status, body) = self;
+        let mut response = body.into_response();
+        response.status_code = status;
+        response.reason_phrase = Response::new(status).reason_phrase;
+        response
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+macro_rules! into_response_via_from {
+    ($($(#[$attr:meta])* $error:ty,)*) => {
+        $(
+            $(#[$attr])*
+            impl IntoResponse for $error {
+                fn into_response(self) -> Response {
+                    self.into()
+                }
+            }
+        )*
+    };
+}
+
+into_response_via_from! {
+    Rejection,
+    ParamError,
+    FormError,
+    MultipartError,
+    #[cfg(feature = "serde")]
+    JsonError,
+}
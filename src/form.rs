@@ -0,0 +1,152 @@
+// `application/x-www-form-urlencoded` decoding, for form bodies and query strings alike.
+//
+//     router.post("/signup", |req: Request| {
+//         let form = match req.form() {
+//             Ok(form) => form,
+//             Err(e) => return e.into(),
+//         };
+//         let interests = form.get_all("interest");
+//         ...
+//     });
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::path_pattern::percent_decode;
+use crate::Response;
+
+#[cfg(feature = "serde")]
+mod de;
+
+#[cfg(feature = "serde")]
+pub(crate) use de::from_params;
+
+// Fields accepted by `Request::form`. The body size itself is capped by
+// `Limits::max_body_size`, and query strings by the request head size limit.
+pub const DEFAULT_MAX_FIELDS: usize = 1000;
+
+// Decoded form fields in their original order. A name may occur several times, as with
+// checkboxes or multi-selects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormData {
+    fields: Vec<(String, String)>,
+}
+
+impl FormData {
+    // Decodes `name=value` pairs separated by `&`. `+` stands for a space and `%XX` for a
+    // byte; a name without `=` has an empty value.
+    pub fn parse(input: &str) -> Self {
+        let fields = input
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(name), decode(value))
+            })
+            .collect();
+        Self { fields }
+    }
+
+    // Like `parse`, but fails rather than decode more than `max_fields` fields.
+    pub fn parse_limited(input: &str, max_fields: usize) -> Result<Self, FormError> {
+        let count = input.split('&').filter(|pair| !pair.is_empty()).count();
+        if count > max_fields {
+            return Err(FormError::TooManyFields(max_fields));
+        }
+        Ok(Self::parse(input))
+    }
+
+    // The first value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.fields
+            .iter()
+            .filter(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|(field, _)| field == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn to_multimap(&self) -> HashMap<String, Vec<String>> {
+        let mut map: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, value) in &self.fields {
+            map.entry(name.clone()).or_default().push(value.clone());
+        }
+        map
+    }
+
+    // Deserializes the fields into `T`. Values are parsed into numbers, booleans and
+    // enums as needed; repeated names fill `Vec`s; empty values become `None` for
+    // `Option`s.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T, FormError> {
+        de::from_fields(&self.fields).map_err(|e| FormError::Invalid(e.to_string()))
+    }
+}
+
+// Decodes one name or value.
+pub fn decode(component: &str) -> String {
+    percent_decode(&component.replace('+', " "))
+}
+
+// Why a form could not be read. Converts into a 415, 413 or 422 response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormError {
+    UnsupportedMediaType(Option<String>),
+    TooManyFields(usize),
+    Invalid(String),
+}
+
+impl FormError {
+    pub fn status(&self) -> u16 {
+        match self {
+            FormError::UnsupportedMediaType(_) => 415,
+            FormError::TooManyFields(_) => 413,
+            FormError::Invalid(_) => 422,
+        }
+    }
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormError::UnsupportedMediaType(Some(media_type)) => {
+                write!(f, "expected application/x-www-form-urlencoded, got {}", media_type)
+            }
+            FormError::UnsupportedMediaType(None) => write!(f, "expected application/x-www-form-urlencoded"),
+            FormError::TooManyFields(max) => write!(f, "more than {} form fields", max),
+            FormError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FormError {}
+
+impl From<FormError> for Response {
+    fn from(err: FormError) -> Self {
+        let response = Response::new(err.status());
+        let body = format!("{} {}: {}", response.status_code, response.reason_phrase, err);
+        response.with_body(&body)
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// Case-insensitive, multi-valued header map that keeps insertion order and the original
+// spelling of header names for serialization.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+    index: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidHeader {
+    Name(String),
+    Value(String),
+}
+
+impl fmt::Display for InvalidHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidHeader::Name(name) => write!(f, "invalid header name {:?}", name),
+            InvalidHeader::Value(name) => write!(f, "invalid value for header {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for InvalidHeader {}
+
+// Parsed `Authorization` header, e.g. `Bearer abc` -> scheme `Bearer`, credentials `abc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Authorization<'a> {
+    pub scheme: &'a str,
+    pub credentials: &'a str,
+}
+
+impl<'a> Authorization<'a> {
+    pub fn bearer(&self) -> Option<&'a str> {
+        self.scheme.eq_ignore_ascii_case("Bearer").then_some(self.credentials)
+    }
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(&name.to_ascii_lowercase())
+    }
+
+    // Returns the first value for `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).next()
+    }
+
+    pub fn get_all<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let positions = self
+            .index
+            .get(&name.to_ascii_lowercase())
+            .map(|positions| positions.as_slice())
+            .unwrap_or_default();
+        positions.iter().map(|&i| self.entries[i].1.as_str())
+    }
+
+    // Replaces every existing value of `name` with `value`.
+    pub fn insert(&mut self, name: &str, value: &str) -> Result<(), InvalidHeader> {
+        validate(name, value)?;
+        self.remove(name);
+        self.push(name, value);
+        Ok(())
+    }
+
+    // Adds `value` without touching existing values of `name`.
+    pub fn append(&mut self, name: &str, value: &str) -> Result<(), InvalidHeader> {
+        validate(name, value)?;
+        self.push(name, value);
+        Ok(())
+    }
+
+    // Removes every value of `name`, returning them in order.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let Some(positions) = self.index.remove(&name.to_ascii_lowercase()) else {
+            return Vec::new();
+        };
+
+        let mut removed = Vec::with_capacity(positions.len());
+        for &i in positions.iter().rev() {
+            removed.push(self.entries.remove(i).1);
+        }
+        removed.reverse();
+        self.reindex();
+        removed
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.get("Content-Length")?.trim().parse().ok()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("Content-Type")
+    }
+
+    // The media type of `Content-Type` without parameters, lowercased.
+    pub fn mime_type(&self) -> Option<String> {
+        let content_type = self.content_type()?;
+        Some(content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase())
+    }
+
+    // Name/value pairs from all `Cookie` headers.
+    pub fn cookies(&self) -> Vec<(&str, &str)> {
+        self.get_all("Cookie")
+            .flat_map(|header| header.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some((name.trim(), value))
+            })
+            .filter(|(name, _)| !name.is_empty())
+            .collect()
+    }
+
+    pub fn authorization(&self) -> Option<Authorization<'_>> {
+        let header = self.get("Authorization")?.trim();
+        let (scheme, credentials) = header.split_once(' ').unwrap_or((header, ""));
+        Some(Authorization {
+            scheme,
+            credentials: credentials.trim(),
+        })
+    }
+
+    fn push(&mut self, name: &str, value: &str) {
+        self.index
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .push(self.entries.len());
+        self.entries.push((name.to_string(), value.to_string()));
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, (name, _)) in self.entries.iter().enumerate() {
+            self.index.entry(name.to_ascii_lowercase()).or_default().push(i);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl PartialEq for HeaderMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for HeaderMap {}
+
+// Header names must be RFC 9110 tokens.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+        })
+}
+
+// Header values may not contain control characters other than horizontal tab,
+// which in particular rules out CR/LF response splitting.
+pub fn is_valid_value(value: &str) -> bool {
+    value.bytes().all(|b| b == b'\t' || (b >= 0x20 && b != 0x7f))
+}
+
+// Splits a header value such as `form-data; name="file"; filename="a;b.txt"` into its
+// leading value and `name=value` parameters. Parameter names are lowercased; quoted
+// values are unquoted.
+pub fn parse_params(value: &str) -> (&str, Vec<(String, String)>) {
+    let (first, mut rest) = value.split_once(';').unwrap_or((value, ""));
+    let mut params = Vec::new();
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        if rest.is_empty() {
+            break;
+        }
+        let end = rest.find(['=', ';']).unwrap_or(rest.len());
+        let name = rest[..end].trim().to_ascii_lowercase();
+        rest = &rest[end..];
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            continue;
+        };
+
+        let after_eq = after_eq.trim_start();
+        let param_value;
+        if let Some(quoted) = after_eq.strip_prefix('"') {
+            let mut unquoted = String::new();
+            let mut chars = quoted.char_indices();
+            let mut consumed = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next().map(|(_, escaped)| escaped)),
+                    '"' => {
+                        consumed = i + 1;
+                        break;
+                    }
+                    c => unquoted.push(c),
+                }
+            }
+            param_value = unquoted;
+            rest = &quoted[consumed..];
+        } else {
+            let end = after_eq.find(';').unwrap_or(after_eq.len());
+            param_value = after_eq[..end].trim().to_string();
+            rest = &after_eq[end..];
+        }
+        params.push((name, param_value));
+    }
+    (first.trim(), params)
+}
+
+// For middleware builders: header values taken from configuration are checked once, so
+// a bad value panics at startup rather than in every response.
+pub(crate) fn expect_valid(name: &str, value: &str) {
+    if let Err(e) = validate(name, value) {
+        panic!("Invalid middleware configuration: {}", e);
+    }
+}
+
+fn validate(name: &str, value: &str) -> Result<(), InvalidHeader> {
+    if !is_valid_name(name) {
+        return Err(InvalidHeader::Name(name.to_string()));
+    }
+    if !is_valid_value(value) {
+        return Err(InvalidHeader::Value(name.to_string()));
+    }
+    Ok(())
+}
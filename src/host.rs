@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+// A host pattern used for virtual hosting. Supported forms:
+// - `api.example.com`      exact match
+// - `*.example.com`        any subdomain (one or more labels), not the apex
+// - `{tenant}.example.com` a single label, exposed to handlers as `req.param("tenant")`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPattern {
+    pattern: String,
+    labels: Vec<HostLabel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostLabel {
+    Literal(String),
+    Capture(String),
+    Wildcard,
+}
+
+impl HostPattern {
+    pub fn parse(pattern: &str) -> Self {
+        let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+        let labels: Vec<HostLabel> = pattern
+            .split('.')
+            .map(|label| {
+                if label == "*" {
+                    HostLabel::Wildcard
+                } else if let Some(name) = label.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+                    HostLabel::Capture(name.to_string())
+                } else {
+                    HostLabel::Literal(label.to_string())
+                }
+            })
+            .collect();
+
+        if labels.iter().skip(1).any(|label| *label == HostLabel::Wildcard) {
+            panic!("Invalid host pattern '{}': '*' is only allowed as the first label", pattern);
+        }
+
+        Self { pattern, labels }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.labels.iter().all(|label| matches!(label, HostLabel::Literal(_)))
+    }
+
+    // Orders overlapping patterns: more literal labels first, then captures before a
+    // wildcard. An exact host name beats any pattern that matches the same host.
+    pub fn specificity(&self) -> (usize, usize) {
+        let literals = self
+            .labels
+            .iter()
+            .filter(|label| matches!(label, HostLabel::Literal(_)))
+            .count();
+        let fixed = self.labels.iter().filter(|label| **label != HostLabel::Wildcard).count();
+        (literals, fixed)
+    }
+
+    // Returns the captured labels if `host` (already stripped of its port) matches.
+    pub fn matches(&self, host: &str) -> Option<HashMap<String, String>> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let host_labels: Vec<&str> = host.split('.').collect();
+
+        let (fixed, wildcard) = match self.labels.first() {
+            Some(HostLabel::Wildcard) => (&self.labels[1..], true),
+            _ => (&self.labels[..], false),
+        };
+
+        if host_labels.len() < fixed.len()
+            || (wildcard && host_labels.len() == fixed.len())
+            || (!wildcard && host_labels.len() != fixed.len())
+        {
+            return None;
+        }
+
+        let offset = host_labels.len() - fixed.len();
+        if host_labels[..offset].iter().any(|label| label.is_empty()) {
+            return None;
+        }
+
+        let mut captures = HashMap::new();
+        for (label, value) in fixed.iter().zip(&host_labels[offset..]) {
+            match label {
+                HostLabel::Literal(literal) if literal == value => {}
+                HostLabel::Capture(name) if !value.is_empty() => {
+                    captures.insert(name.clone(), value.to_string());
+                }
+                _ => return None,
+            }
+        }
+        Some(captures)
+    }
+}
+
+// Strips the port (and IPv6 brackets) from a `Host` header value.
+pub fn strip_port(host: &str) -> &str {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    host.split(':').next().unwrap_or(host)
+}
@@ -0,0 +1,85 @@
+// JSON request bodies and responses, behind the `serde` feature.
+//
+//     #[derive(Deserialize)]
+//     struct NewUser { name: String }
+//
+//     router.post("/users", |req: Request| {
+//         let user: NewUser = match req.json() {
+//             Ok(user) => user,
+//             Err(e) => return e.into(),
+//         };
+//         Response::new(201).with_json(&json!({ "name": user.name }))
+//     });
+
+use serde_json::error::Category;
+use std::fmt;
+
+use crate::Response;
+
+// Why a request body could not be read as JSON. Converts into the matching error
+// response: 415 for a missing or non-JSON `Content-Type`, 400 for malformed JSON and
+// 422 for JSON that doesn't fit the target type.
+#[derive(Debug)]
+pub enum JsonError {
+    UnsupportedMediaType(Option<String>),
+    Syntax(serde_json::Error),
+    Data(serde_json::Error),
+}
+
+impl JsonError {
+    pub(crate) fn from_serde(err: serde_json::Error) -> Self {
+        match err.classify() {
+            Category::Data => JsonError::Data(err),
+            _ => JsonError::Syntax(err),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            JsonError::UnsupportedMediaType(_) => 415,
+            JsonError::Syntax(_) => 400,
+            JsonError::Data(_) => 422,
+        }
+    }
+
+    // Where in the body parsing failed, as 1-based line and column.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            JsonError::UnsupportedMediaType(_) => None,
+            JsonError::Syntax(err) | JsonError::Data(err) => Some((err.line(), err.column())),
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::UnsupportedMediaType(Some(media_type)) => {
+                write!(f, "expected application/json, got {}", media_type)
+            }
+            JsonError::UnsupportedMediaType(None) => write!(f, "expected application/json"),
+            // serde_json appends the location itself.
+            JsonError::Syntax(err) | JsonError::Data(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+// A JSON body such as
+// `{"error": "missing field `name` at line 1 column 2", "line": 1, "column": 2}`.
+impl From<JsonError> for Response {
+    fn from(err: JsonError) -> Self {
+        let mut body = serde_json::json!({ "error": err.to_string() });
+        if let Some((line, column)) = err.location() {
+            body["line"] = line.into();
+            body["column"] = column.into();
+        }
+        Response::new(err.status()).with_json(&body)
+    }
+}
+
+// `application/json` and structured syntax suffixes like `application/problem+json`.
+pub(crate) fn is_json(mime_type: &str) -> bool {
+    mime_type == "application/json" || (mime_type.starts_with("application/") && mime_type.ends_with("+json"))
+}
@@ -1,17 +1,36 @@
+pub mod cookie;
+pub mod extensions;
+pub mod extract;
+pub mod form;
 pub mod handler;
+pub mod header;
+pub mod host;
 pub mod http_method;
+#[cfg(feature = "serde")]
+pub mod json;
 pub mod logger;
 pub mod middleware;
+pub mod multipart;
+pub mod negotiation;
+pub mod path_pattern;
 pub mod request;
 pub mod response;
 pub mod router;
 pub mod server;
 
+#[cfg(feature = "serde")]
+pub use extract::{Form, Json, Path, Query};
+pub use extract::{extract, Extension, FromRequest, IntoResponse, Rejection, State};
+pub use form::{FormData, FormError};
 pub use handler::Handler;
+pub use header::HeaderMap;
 pub use http_method::HttpMethod;
-pub use logger::Logger;
+#[cfg(feature = "serde")]
+pub use json::JsonError;
+pub use logger::{Logger, RequestLogger};
 pub use middleware::{Middleware, NextFn};
-pub use request::Request;
+pub use multipart::{Multipart, MultipartError};
+pub use request::{BodyReader, ParamError, ParseRequestError, Request, RequestId, TlsInfo};
 pub use response::Response;
-pub use router::Router;
-pub use server::Server;
+pub use router::{RouteInfo, Router};
+pub use server::{Limits, Server, Timeouts};
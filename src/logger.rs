@@ -1,42 +1,141 @@
 use chrono::Local;
 use std::error::Error;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::fs::File;
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
+use crate::request::RequestId;
+use crate::{HttpMethod, Request, Response};
+
 #[derive(Clone)]
 pub struct Logger {
     file: Arc<Mutex<File>>,
+    error_file: Option<Arc<Mutex<File>>>,
 }
 
 impl Logger {
     pub async fn new(log_file_path: &str) -> Result<Self, Box<dyn Error>> {
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(log_file_path)
-            .await?;
-
         Ok(Self {
-            file: Arc::new(Mutex::new(file)),
+            file: Arc::new(Mutex::new(open(log_file_path).await?)),
+            error_file: None,
         })
     }
 
+    // Sends `error` entries to a separate file instead of the main log.
+    pub async fn with_error_log(mut self, error_log_path: &str) -> Result<Self, Box<dyn Error>> {
+        self.error_file = Some(Arc::new(Mutex::new(open(error_log_path).await?)));
+        Ok(self)
+    }
+
     pub async fn log(&self, message: &str) {
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let log_line = format!("[{}] {}", timestamp, message);
+        write_line(&self.file, message).await;
+    }
+
+    // One access log line per request, ending with the request ID when the `RequestIds`
+    // middleware assigned one.
+    pub async fn access(&self, request: &Request, response: &Response) {
+        self.access_line(&RequestLine::from(request), response).await;
+    }
+
+    pub(crate) async fn access_line(&self, request: &RequestLine, response: &Response) {
+        let ip = request
+            .remote_addr
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let method = &request.method;
+        let path = &request.path;
+        let version = &request.version;
+        let status = response.status_code;
+        let size = response.body.len();
+
+        let mut entry = format!("{ip} - \"{method} {path} {version}\" {status} {size}");
+        if let Some(id) = response.extensions.get::<RequestId>() {
+            entry.push_str(&format!(" request_id={}", id));
+        }
+        entry.push('\n');
+        self.log(&entry).await;
+    }
+
+    pub async fn error(&self, request_id: Option<&str>, message: &str) {
+        let entry = match request_id {
+            Some(id) => format!("[error] request_id={} {}\n", id, message),
+            None => format!("[error] {}\n", message),
+        };
+        write_line(self.error_file.as_ref().unwrap_or(&self.file), &entry).await;
+    }
+}
 
-        let mut file = self.file.lock().await;
-        if let Err(e) = file.write_all(log_line.as_bytes()).await {
-            eprintln!("[!] Failed to write to log file: {}", e);
+// Error logging for one request from synchronous code, see `Request::logger`. Lines are
+// written in the background, so logging never blocks a handler. Without a server
+// `Logger` (e.g. when a router is called directly in tests) they go to stderr.
+#[derive(Clone)]
+pub struct RequestLogger {
+    logger: Option<Logger>,
+    request_id: Option<RequestId>,
+}
+
+impl RequestLogger {
+    pub(crate) fn new(logger: Option<Logger>, request_id: Option<RequestId>) -> Self {
+        Self { logger, request_id }
+    }
+
+    pub fn error(&self, message: &str) {
+        let request_id = self.request_id.as_ref().map(|id| id.as_str().to_string());
+        match (&self.logger, tokio::runtime::Handle::try_current()) {
+            (Some(logger), Ok(runtime)) => {
+                let logger = logger.clone();
+                let message = message.to_string();
+                runtime.spawn(async move { logger.error(request_id.as_deref(), &message).await });
+            }
+            _ => match request_id {
+                Some(id) => eprintln!("[error] request_id={} {}", id, message),
+                None => eprintln!("[error] {}", message),
+            },
         }
     }
+}
+
+// The parts of a request the logs use, taken before the request (and its body) is
+// handed to the router.
+pub(crate) struct RequestLine {
+    pub(crate) remote_addr: Option<SocketAddr>,
+    pub(crate) method: HttpMethod,
+    pub(crate) path: String,
+    pub(crate) version: String,
+    // Set when the server assigned the ID before dispatch.
+    pub(crate) request_id: Option<RequestId>,
+}
 
-    pub fn clone(&self) -> Self {
-        Logger {
-            file: Arc::clone(&self.file),
+impl From<&Request> for RequestLine {
+    fn from(request: &Request) -> Self {
+        Self {
+            remote_addr: request.remote_addr,
+            method: request.method.clone(),
+            path: request.path.clone(),
+            version: request.version.clone(),
+            request_id: request.extension::<RequestId>().cloned(),
         }
     }
 }
+
+async fn open(path: &str) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path).await
+}
+
+async fn write_line(file: &Mutex<File>, message: &str) {
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let log_line = format!("[{}] {}", timestamp, message);
+
+    let mut file = file.lock().await;
+    // tokio hands writes to a background thread; flush so the line is on disk on return.
+    let result = match file.write_all(log_line.as_bytes()).await {
+        Ok(()) => file.flush().await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("[!] Failed to write to log file: {}", e);
+    }
+}
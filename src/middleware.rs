@@ -1,10 +1,42 @@
 use crate::{Request, Response};
 use std::sync::Arc;
 
+pub mod auth;
+pub mod compression;
+pub mod cors;
+pub mod csrf;
+pub mod decompression;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+pub mod rate_limit;
+pub mod request_id;
+pub mod security_headers;
+pub mod session;
+
+pub use auth::{BasicAuth, BearerAuth, Htpasswd, Principal};
+pub use compression::Compression;
+pub use cors::Cors;
+pub use csrf::{Csrf, CsrfToken};
+pub use decompression::Decompression;
+#[cfg(feature = "jwt")]
+pub use jwt::{Claims, JwtAuth};
+pub use rate_limit::{RateLimit, RateLimitKey};
+pub use request_id::{RequestId, RequestIds};
+pub use security_headers::{CspNonce, SecurityHeaders};
+pub use session::{FileStore, MemoryStore, Session, SessionStore, Sessions};
+
 pub type NextFn = Box<dyn FnOnce(Request) -> Response + Send>;
 
 pub trait Middleware: Send + Sync {
     fn handle(&self, req: Request, next: NextFn) -> Response;
+
+    // Called for `Expect: 100-continue` requests before the client sends the body, with
+    // the body still empty. Returning a response sends it as the final answer in place of
+    // `100 Continue`, sparing the upload; the middlewares ahead of this one see it in
+    // `handle` as if `next` had returned it. `handle` still runs for accepted requests.
+    fn on_expect_continue(&self, _req: &Request) -> Option<Response> {
+        None
+    }
 }
 
 impl<F> Middleware for F where F: Fn(Request, NextFn) -> Response + Send + Sync {
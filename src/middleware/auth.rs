@@ -0,0 +1,284 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fs, io};
+use subtle::ConstantTimeEq;
+
+use crate::header::expect_valid;
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+// The authenticated identity. Auth middlewares attach it to the request's extensions:
+//
+//     let user = req.extension::<Principal>().map(|p| p.name.as_str());
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub name: String,
+    // The `Authorization` scheme that authenticated the request, e.g. `Basic`.
+    pub scheme: String,
+}
+
+impl Principal {
+    pub fn new(name: &str, scheme: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            scheme: scheme.to_string(),
+        }
+    }
+}
+
+// Checks a username/password pair for `BasicAuth`.
+pub trait CredentialVerifier: Send + Sync {
+    fn verify(&self, username: &str, password: &str) -> bool;
+}
+
+impl<F> CredentialVerifier for F
+where
+    F: Fn(&str, &str) -> bool + Send + Sync,
+{
+    fn verify(&self, username: &str, password: &str) -> bool {
+        (self)(username, password)
+    }
+}
+
+// Resolves a bearer token to a principal for `BearerAuth`; `None` rejects the token.
+pub trait TokenValidator: Send + Sync {
+    fn validate(&self, token: &str) -> Option<Principal>;
+}
+
+impl<F> TokenValidator for F
+where
+    F: Fn(&str) -> Option<Principal> + Send + Sync,
+{
+    fn validate(&self, token: &str) -> Option<Principal> {
+        (self)(token)
+    }
+}
+
+// Compares secrets without an early exit, so response timing does not reveal how many
+// leading bytes matched. Only the length can leak.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+// Credentials from an Apache htpasswd file. Supports bcrypt (`$2y$`, `$2a$`, `$2b$`) and
+// `{SHA}` entries; lines with other hash formats (e.g. `$apr1$`) never verify.
+#[derive(Debug, Clone, Default)]
+pub struct Htpasswd {
+    entries: HashMap<String, String>,
+    // Verified for unknown users, so they take as long as known ones.
+    dummy_hash: Option<String>,
+}
+
+impl Htpasswd {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    // Blank lines and `#` comments are ignored.
+    pub fn parse(contents: &str) -> Self {
+        let entries: HashMap<String, String> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(user, hash)| (user.to_string(), hash.to_string()))
+            .collect();
+        let dummy_hash = entries
+            .values()
+            .filter_map(|hash| bcrypt_cost(hash))
+            .max()
+            .and_then(|cost| bcrypt::hash("dummy password", cost).ok());
+        Self { entries, dummy_hash }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl CredentialVerifier for Htpasswd {
+    fn verify(&self, username: &str, password: &str) -> bool {
+        let Some(hash) = self.entries.get(username) else {
+            // Don't reveal which usernames exist through a faster rejection.
+            if let Some(dummy) = &self.dummy_hash {
+                let _ = bcrypt::verify(password, dummy);
+            }
+            return false;
+        };
+
+        if bcrypt_cost(hash).is_some() {
+            bcrypt::verify(password, hash).unwrap_or(false)
+        } else if let Some(encoded) = hash.strip_prefix("{SHA}") {
+            let digest = STANDARD.encode(Sha1::digest(password.as_bytes()));
+            constant_time_eq(digest.as_bytes(), encoded.as_bytes())
+        } else {
+            false
+        }
+    }
+}
+
+// The cost of a bcrypt hash such as `$2y$10$...`, or `None` for other formats.
+fn bcrypt_cost(hash: &str) -> Option<u32> {
+    let rest = ["$2y$", "$2a$", "$2b$"]
+        .iter()
+        .find_map(|prefix| hash.strip_prefix(prefix))?;
+    rest.get(..2)?.parse().ok()
+}
+
+// HTTP Basic authentication (RFC 7617). Requests without valid credentials get 401 with
+// a `WWW-Authenticate: Basic` challenge; accepted ones carry a `Principal`.
+//
+//     router.use_global(BasicAuth::new("admin", Htpasswd::from_file(".htpasswd")?));
+pub struct BasicAuth {
+    realm: String,
+    verifier: Box<dyn CredentialVerifier>,
+}
+
+impl BasicAuth {
+    pub fn new<V: CredentialVerifier + 'static>(realm: &str, verifier: V) -> Self {
+        expect_valid("WWW-Authenticate", realm);
+        Self {
+            realm: realm.to_string(),
+            verifier: Box::new(verifier),
+        }
+    }
+
+    fn credentials(req: &Request) -> Option<(String, String)> {
+        let auth = req.headers.authorization()?;
+        if !auth.scheme.eq_ignore_ascii_case("Basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(STANDARD.decode(auth.credentials).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+        Some((username.to_string(), password.to_string()))
+    }
+
+    fn challenge(&self) -> Response {
+        Response::new(401).with_body("401 Unauthorized").with_header(
+            "WWW-Authenticate",
+            &format!("Basic realm={}, charset=\"UTF-8\"", quote(&self.realm)),
+        )
+    }
+
+    fn authenticate(&self, req: &Request) -> Option<Principal> {
+        let (username, password) = Self::credentials(req)?;
+        self.verifier
+            .verify(&username, &password)
+            .then(|| Principal::new(&username, "Basic"))
+    }
+}
+
+impl Middleware for BasicAuth {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        match self.authenticate(&req) {
+            Some(principal) => {
+                req.extensions.insert(principal);
+                next(req)
+            }
+            None => self.challenge(),
+        }
+    }
+
+    fn on_expect_continue(&self, req: &Request) -> Option<Response> {
+        self.authenticate(req).is_none().then(|| self.challenge())
+    }
+}
+
+// Bearer token authentication (RFC 6750). A missing token gets a bare challenge; a token
+// the validator rejects gets `error="invalid_token"`.
+//
+//     router.use_global(BearerAuth::with_tokens(&[("s3cr3t", "deploy-bot")]));
+pub struct BearerAuth {
+    realm: Option<String>,
+    validator: Box<dyn TokenValidator>,
+}
+
+impl BearerAuth {
+    pub fn new<V: TokenValidator + 'static>(validator: V) -> Self {
+        Self {
+            realm: None,
+            validator: Box::new(validator),
+        }
+    }
+
+    // Accepts a fixed set of `(token, principal name)` pairs, compared in constant time.
+    pub fn with_tokens(tokens: &[(&str, &str)]) -> Self {
+        let tokens: Vec<(String, String)> = tokens
+            .iter()
+            .map(|(token, name)| (token.to_string(), name.to_string()))
+            .collect();
+        Self::new(move |candidate: &str| {
+            // Check every token so the position of a match is not observable.
+            let mut found = None;
+            for (token, name) in &tokens {
+                if constant_time_eq(token.as_bytes(), candidate.as_bytes()) {
+                    found = Some(name);
+                }
+            }
+            found.map(|name| Principal::new(name, "Bearer"))
+        })
+    }
+
+    pub fn realm(mut self, realm: &str) -> Self {
+        expect_valid("WWW-Authenticate", realm);
+        self.realm = Some(realm.to_string());
+        self
+    }
+
+    fn challenge(&self, error: Option<&str>) -> Response {
+        let mut params = Vec::new();
+        if let Some(realm) = &self.realm {
+            params.push(format!("realm={}", quote(realm)));
+        }
+        if let Some(error) = error {
+            params.push(format!("error={}", quote(error)));
+        }
+        let challenge = if params.is_empty() {
+            "Bearer".to_string()
+        } else {
+            format!("Bearer {}", params.join(", "))
+        };
+        Response::new(401)
+            .with_body("401 Unauthorized")
+            .with_header("WWW-Authenticate", &challenge)
+    }
+
+    // Fails with the `error` parameter for the challenge.
+    fn authenticate(&self, req: &Request) -> Result<Principal, Option<&'static str>> {
+        let token = req
+            .headers
+            .authorization()
+            .and_then(|auth| auth.bearer())
+            .filter(|token| !token.is_empty())
+            .ok_or(None)?;
+        self.validator.validate(token).ok_or(Some("invalid_token"))
+    }
+}
+
+impl Middleware for BearerAuth {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        match self.authenticate(&req) {
+            Ok(principal) => {
+                req.extensions.insert(principal);
+                next(req)
+            }
+            Err(error) => self.challenge(error),
+        }
+    }
+
+    fn on_expect_continue(&self, req: &Request) -> Option<Response> {
+        self.authenticate(req).err().map(|error| self.challenge(error))
+    }
+}
+
+// Formats a challenge parameter as a quoted-string.
+pub(crate) fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
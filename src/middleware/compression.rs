@@ -0,0 +1,154 @@
+use std::io::Write;
+
+use brotli::CompressorWriter;
+use flate2::write::{DeflateEncoder, GzEncoder};
+
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+// Compresses response bodies with brotli, gzip or deflate, whichever the client prefers
+// in `Accept-Encoding` (ties go to that order). Bodies below `min_size`, responses that
+// already have a `Content-Encoding` and already-compressed media types are left alone.
+// A strong `ETag` is weakened on compressed responses, since it names the uncompressed
+// bytes. Response bodies are always fully buffered (there are no streaming bodies yet),
+// so each one is compressed in a single pass once the handler has returned.
+pub struct Compression {
+    min_size: usize,
+    level: u32,
+    encodings: Vec<&'static str>,
+}
+
+// Media types whose payload is already compressed, so compressing again only costs CPU.
+const COMPRESSED_TYPES: &[&str] = &[
+    "application/gzip",
+    "application/x-gzip",
+    "application/zip",
+    "application/zstd",
+    "application/x-7z-compressed",
+    "application/x-bzip2",
+    "application/x-rar-compressed",
+    "application/x-xz",
+    "application/pdf",
+    "application/octet-stream",
+    "font/woff",
+    "font/woff2",
+];
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    pub fn new() -> Self {
+        Self {
+            min_size: 1024,
+            level: 6,
+            encodings: vec!["br", "gzip", "deflate"],
+        }
+    }
+
+    // Bodies smaller than this many bytes are sent uncompressed.
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    // Compression level from 0 (fastest) to 9 (smallest), shared by all encodings.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    // Restricts and orders the encodings offered, e.g. `&["gzip"]`.
+    pub fn encodings(mut self, encodings: &[&'static str]) -> Self {
+        self.encodings = encodings
+            .iter()
+            .copied()
+            .filter(|e| matches!(*e, "br" | "gzip" | "deflate"))
+            .collect();
+        self
+    }
+
+    fn is_compressible(response: &Response) -> bool {
+        if response.status_code < 200 || response.status_code == 204 || response.status_code == 304 {
+            return false;
+        }
+        if response.headers.contains("Content-Encoding") {
+            return false;
+        }
+
+        match response.headers.mime_type() {
+            Some(mime) => {
+                let (kind, subtype) = mime.split_once('/').unwrap_or((&mime, ""));
+                let compressed_media = matches!(kind, "image" | "video" | "audio")
+                    && !subtype.ends_with("+xml");
+                !compressed_media && !COMPRESSED_TYPES.contains(&mime.as_str())
+            }
+            None => true,
+        }
+    }
+
+    fn compress(&self, encoding: &str, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match encoding {
+            "br" => {
+                let mut writer = CompressorWriter::new(Vec::new(), 4096, self.level, 22);
+                writer.write_all(body)?;
+                Ok(writer.into_inner())
+            }
+            "gzip" => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::new(self.level));
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            _ => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::new(self.level));
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, req: Request, next: NextFn) -> Response {
+        let mut available = self.encodings.clone();
+        available.push("identity");
+        let encoding = req.negotiate_encoding(&available);
+        let logger = req.logger();
+
+        let mut response = next(req);
+        if !Self::is_compressible(&response) {
+            return response;
+        }
+        response.append_header("Vary", "Accept-Encoding");
+
+        let encoding = match encoding {
+            Some(encoding) if encoding != "identity" => encoding,
+            _ => return response,
+        };
+        if response.body.len() < self.min_size {
+            return response;
+        }
+
+        match self.compress(encoding, &response.body) {
+            Ok(compressed) if compressed.len() < response.body.len() => {
+                response.body = compressed;
+                response.set_header("Content-Encoding", encoding);
+                // The auto-inserted Content-Length is recomputed from the new body
+                if response.headers.contains("Content-Length") {
+                    response.set_header("Content-Length", &response.body.len().to_string());
+                }
+                // Conditional requests must not match this against the uncompressed bytes.
+                if let Some(etag) = response.headers.get("ETag").filter(|etag| !etag.starts_with("W/")) {
+                    let weak = format!("W/{}", etag);
+                    response.set_header("ETag", &weak);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => logger.error(&format!("response compression with {} failed: {}", encoding, e)),
+        }
+        response
+    }
+}
@@ -0,0 +1,262 @@
+use regex::Regex;
+use std::time::Duration;
+
+use crate::header::{expect_valid, is_valid_name};
+use crate::http_method::HttpMethod;
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+// Cross-Origin Resource Sharing. Register it with `Router::use_global` so preflight
+// `OPTIONS` requests are answered before routing, even for paths without an OPTIONS route.
+//
+//     router.use_global(
+//         Cors::new()
+//             .allow_origin("https://app.example.com")
+//             .allow_origin("https://*.example.com")
+//             .allow_methods(&[HttpMethod::GET, HttpMethod::POST])
+//             .allow_credentials(true),
+//     );
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: AllowedOrigins,
+    methods: Vec<HttpMethod>,
+    headers: AllowedHeaders,
+    exposed_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+enum AllowedOrigins {
+    Any,
+    List(Vec<OriginPattern>),
+}
+
+#[derive(Debug, Clone)]
+enum AllowedHeaders {
+    Any,
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+enum OriginPattern {
+    Exact(String),
+    Wildcard(Regex),
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> Self {
+        let pattern = pattern.trim().trim_end_matches('/').to_ascii_lowercase();
+        if !pattern.contains('*') {
+            return OriginPattern::Exact(pattern);
+        }
+
+        // `*` stands for one or more DNS labels, so `https://*.example.com` does not
+        // match `https://evil-example.com` or `https://example.com`.
+        let regex = pattern
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join("[a-z0-9-]+(?:\\.[a-z0-9-]+)*");
+        OriginPattern::Wildcard(Regex::new(&format!("^{}$", regex)).expect("valid origin pattern"))
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(exact) => *exact == origin,
+            OriginPattern::Wildcard(regex) => regex.is_match(origin),
+        }
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cors {
+    // No origins are allowed until configured; methods default to GET, HEAD and POST.
+    pub fn new() -> Self {
+        Self {
+            origins: AllowedOrigins::List(Vec::new()),
+            methods: vec![HttpMethod::GET, HttpMethod::HEAD, HttpMethod::POST],
+            headers: AllowedHeaders::List(Vec::new()),
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+
+    // Any origin, any request header and all common methods. Meant for development.
+    pub fn permissive() -> Self {
+        Self::new()
+            .allow_any_origin()
+            .allow_any_header()
+            .allow_methods(&[
+                HttpMethod::GET,
+                HttpMethod::HEAD,
+                HttpMethod::POST,
+                HttpMethod::PUT,
+                HttpMethod::PATCH,
+                HttpMethod::DELETE,
+            ])
+    }
+
+    // Allows an exact origin (`https://app.example.com`) or a pattern (`https://*.example.com`).
+    // Panics if `origin` can't be sent as a header value.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        expect_valid("Access-Control-Allow-Origin", origin);
+        let pattern = OriginPattern::parse(origin);
+        match &mut self.origins {
+            AllowedOrigins::List(origins) => origins.push(pattern),
+            AllowedOrigins::Any => self.origins = AllowedOrigins::List(vec![pattern]),
+        }
+        self
+    }
+
+    // Panics together with `allow_credentials(true)`: echoing every origin with
+    // credentials would let any site read authenticated responses.
+    pub fn allow_any_origin(mut self) -> Self {
+        if self.allow_credentials {
+            panic!("Invalid CORS configuration: allow_any_origin can't be combined with allow_credentials");
+        }
+        self.origins = AllowedOrigins::Any;
+        self
+    }
+
+    pub fn allow_methods(mut self, methods: &[HttpMethod]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    // Panics on names that aren't valid header names, as do `expose_headers`.
+    pub fn allow_headers(mut self, headers: &[&str]) -> Self {
+        for header in headers {
+            expect_valid(header, "");
+        }
+        self.headers = AllowedHeaders::List(headers.iter().map(|h| h.to_ascii_lowercase()).collect());
+        self
+    }
+
+    // Mirrors whatever the preflight asks for in `Access-Control-Request-Headers`.
+    pub fn allow_any_header(mut self) -> Self {
+        self.headers = AllowedHeaders::Any;
+        self
+    }
+
+    pub fn expose_headers(mut self, headers: &[&str]) -> Self {
+        for header in headers {
+            expect_valid(header, "");
+        }
+        self.exposed_headers = headers.iter().map(|h| h.to_string()).collect();
+        self
+    }
+
+    // Requires an explicit origin list; see `allow_any_origin`.
+    pub fn allow_credentials(mut self, allow: bool) -> Self {
+        if allow && matches!(self.origins, AllowedOrigins::Any) {
+            panic!("Invalid CORS configuration: allow_credentials can't be combined with allow_any_origin");
+        }
+        self.allow_credentials = allow;
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    fn is_origin_allowed(&self, origin: &str) -> bool {
+        match &self.origins {
+            AllowedOrigins::Any => true,
+            AllowedOrigins::List(patterns) => {
+                let origin = origin.to_ascii_lowercase();
+                patterns.iter().any(|pattern| pattern.matches(&origin))
+            }
+        }
+    }
+
+    fn allow_origin_value(&self, origin: &str) -> String {
+        match self.origins {
+            AllowedOrigins::Any => "*".to_string(),
+            AllowedOrigins::List(_) => origin.to_string(),
+        }
+    }
+
+    fn handle_preflight(&self, req: &Request, origin: &str, requested_method: &str) -> Response {
+        let method_allowed = requested_method
+            .parse::<HttpMethod>()
+            .map(|method| self.methods.contains(&method))
+            .unwrap_or(false);
+
+        let requested_headers: Vec<String> = req
+            .header("Access-Control-Request-Headers")
+            .unwrap_or("")
+            .split(',')
+            .map(|h| h.trim().to_ascii_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        let headers_allowed = match &self.headers {
+            AllowedHeaders::Any => requested_headers.iter().all(|h| is_valid_name(h)),
+            AllowedHeaders::List(allowed) => requested_headers.iter().all(|h| allowed.contains(h)),
+        };
+
+        if !self.is_origin_allowed(origin) || !method_allowed || !headers_allowed {
+            return Response::new(403).with_body("403 Forbidden: CORS preflight rejected");
+        }
+
+        let methods: Vec<String> = self.methods.iter().map(|m| m.to_string()).collect();
+        let allowed_headers = match &self.headers {
+            AllowedHeaders::Any => requested_headers.join(", "),
+            AllowedHeaders::List(allowed) => allowed.join(", "),
+        };
+
+        let mut response = Response::new(204)
+            .with_header("Access-Control-Allow-Origin", &self.allow_origin_value(origin))
+            .with_header("Access-Control-Allow-Methods", &methods.join(", "))
+            .with_header(
+                "Vary",
+                "Origin, Access-Control-Request-Method, Access-Control-Request-Headers",
+            );
+        if !allowed_headers.is_empty() {
+            response.set_header("Access-Control-Allow-Headers", &allowed_headers);
+        }
+        if self.allow_credentials {
+            response.set_header("Access-Control-Allow-Credentials", "true");
+        }
+        if let Some(max_age) = self.max_age {
+            response.set_header("Access-Control-Max-Age", &max_age.as_secs().to_string());
+        }
+        response
+    }
+}
+
+impl Middleware for Cors {
+    fn handle(&self, req: Request, next: NextFn) -> Response {
+        let Some(origin) = req.header("Origin").map(str::to_string) else {
+            return next(req);
+        };
+
+        if req.method == HttpMethod::OPTIONS {
+            if let Some(requested_method) = req.header("Access-Control-Request-Method") {
+                return self.handle_preflight(&req, &origin, requested_method);
+            }
+        }
+
+        let mut response = next(req);
+        if self.is_origin_allowed(&origin) {
+            response.set_header("Access-Control-Allow-Origin", &self.allow_origin_value(&origin));
+            if self.allow_credentials {
+                response.set_header("Access-Control-Allow-Credentials", "true");
+            }
+            if !self.exposed_headers.is_empty() {
+                response.set_header("Access-Control-Expose-Headers", &self.exposed_headers.join(", "));
+            }
+        }
+        if !matches!(self.origins, AllowedOrigins::Any) {
+            response.append_header("Vary", "Origin");
+        }
+        response
+    }
+}
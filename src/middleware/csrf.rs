@@ -0,0 +1,282 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::sync::{Arc, OnceLock};
+
+#[cfg(feature = "secure-cookies")]
+use crate::cookie::CookieKey;
+use crate::cookie::{Cookie, SameSite};
+use crate::http_method::HttpMethod;
+use crate::middleware::auth::constant_time_eq;
+use crate::middleware::session::Session;
+use crate::middleware::{Middleware, NextFn};
+use crate::path_pattern::PathPattern;
+use crate::{Request, Response};
+
+// Session key under which synchronizer tokens are stored.
+const SESSION_KEY: &str = "csrf_token";
+
+// The CSRF token for the current request, for embedding in forms and pages:
+//
+//     let token = req.extension::<CsrfToken>().unwrap();
+//     format!("<input type=\"hidden\" name=\"csrf_token\" value=\"{}\">", token.as_str())
+//
+// With `Csrf::synchronizer`, a session without a token only gets one (and so only gets
+// stored) when a handler first reads it, so anonymous traffic doesn't fill the store.
+#[derive(Clone)]
+pub struct CsrfToken {
+    token: Arc<OnceLock<String>>,
+    // Where a token issued on first use is kept.
+    session: Option<Session>,
+}
+
+impl CsrfToken {
+    fn issued(token: String) -> Self {
+        Self {
+            token: Arc::new(OnceLock::from(token)),
+            session: None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.token.get_or_init(|| {
+            let token = generate_token();
+            if let Some(session) = &self.session {
+                session.insert(SESSION_KEY, &token);
+            }
+            token
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Mode {
+    // The token lives in the session; requires `Sessions` to run first.
+    Synchronizer,
+    // The token lives in a cookie that the request must echo. The cookie carries an HMAC
+    // of the token, bound to the session when there is one, so a cookie planted from a
+    // sibling subdomain does not verify.
+    #[cfg(feature = "secure-cookies")]
+    DoubleSubmit(CookieKey),
+}
+
+// Cross-site request forgery protection. Requests with unsafe methods (anything but
+// GET, HEAD, OPTIONS and TRACE) must carry the token in the `X-CSRF-Token` header or a
+// `csrf_token` form field, and their `Origin` (or, failing that, `Referer`) must be the
+// server's own origin or a trusted one. Violations get 403.
+//
+// The server's own origin is taken from `Host` and the connection's scheme. Behind a
+// proxy that terminates TLS, set it with `origin` instead.
+//
+//     router.use_global(Sessions::new(MemoryStore::new()));
+//     router.use_global(Csrf::synchronizer().exempt("/webhooks/{provider}"));
+pub struct Csrf {
+    mode: Mode,
+    header: String,
+    field: String,
+    cookie_name: String,
+    secure_cookie: bool,
+    origin: Option<String>,
+    trusted_origins: Vec<String>,
+    exempt: Vec<PathPattern>,
+}
+
+impl Csrf {
+    pub fn synchronizer() -> Self {
+        Self::with_mode(Mode::Synchronizer)
+    }
+
+    // For stateless apps. The cookie is readable by JavaScript so clients can copy the
+    // token (the part before the last `.`) into the header. `key` signs the cookie; use a
+    // fixed key so tokens survive restarts.
+    #[cfg(feature = "secure-cookies")]
+    pub fn double_submit(key: CookieKey) -> Self {
+        Self::with_mode(Mode::DoubleSubmit(key))
+    }
+
+    fn with_mode(mode: Mode) -> Self {
+        Self {
+            mode,
+            header: "X-CSRF-Token".to_string(),
+            field: "csrf_token".to_string(),
+            cookie_name: "csrf_token".to_string(),
+            secure_cookie: true,
+            origin: None,
+            trusted_origins: Vec::new(),
+            exempt: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = name.to_string();
+        self
+    }
+
+    pub fn field(mut self, name: &str) -> Self {
+        self.field = name.to_string();
+        self
+    }
+
+    // Cookie used by `double_submit`.
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    // Whether the `double_submit` cookie is `Secure`. On by default.
+    pub fn secure_cookie(mut self, secure: bool) -> Self {
+        self.secure_cookie = secure;
+        self
+    }
+
+    // The server's public origin, e.g. `https://app.example.com`. Replaces the one derived
+    // from the request, which says `http` when a proxy terminates TLS.
+    pub fn origin(mut self, origin: &str) -> Self {
+        self.origin = Some(origin.trim_end_matches('/').to_ascii_lowercase());
+        self
+    }
+
+    // Another origin allowed to submit, e.g. `https://admin.example.com`.
+    pub fn trusted_origin(mut self, origin: &str) -> Self {
+        self.trusted_origins
+            .push(origin.trim_end_matches('/').to_ascii_lowercase());
+        self
+    }
+
+    // Skips all checks for paths matching `pattern` (route pattern syntax), e.g. webhooks
+    // authenticated by other means.
+    pub fn exempt(mut self, pattern: &str) -> Self {
+        self.exempt.push(PathPattern::parse(pattern));
+        self
+    }
+
+    fn origin_allowed(&self, req: &Request) -> bool {
+        let origin = match req.header("Origin") {
+            Some(origin) => origin.to_string(),
+            None => match req.header("Referer").and_then(origin_of) {
+                Some(origin) => origin,
+                // Neither header: non-browser client or stripped by privacy settings.
+                // The token check still applies.
+                None => return true,
+            },
+        };
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+
+        let own = self.origin.clone().or_else(|| {
+            req.header("Host")
+                .map(|host| format!("{}://{}", req.scheme(), host.to_ascii_lowercase()))
+        });
+        own.as_deref() == Some(origin.as_str()) || self.trusted_origins.contains(&origin)
+    }
+
+    // What double-submit tokens are signed for: this cookie and, once there is one, the
+    // session.
+    #[cfg(feature = "secure-cookies")]
+    fn signing_context(&self, req: &Request) -> String {
+        let session_id = req.extension::<Session>().and_then(Session::id).unwrap_or_default();
+        format!("csrf:{}:{}", self.cookie_name, session_id)
+    }
+
+    fn submitted_token(&self, req: &Request) -> Option<String> {
+        if let Some(token) = req.header(&self.header) {
+            return Some(token.trim().to_string());
+        }
+        let form = req.form().ok()?;
+        form.get(&self.field).map(str::to_string)
+    }
+}
+
+impl Middleware for Csrf {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        if self.exempt.iter().any(|pattern| pattern.matches(&req.path).is_some()) {
+            return next(req);
+        }
+
+        // The client's token (`None` if it has none yet), the token handed to handlers,
+        // and the cookie value to set when a new one was issued.
+        let (expected, token, issued_cookie): (Option<String>, CsrfToken, Option<String>) = match &self.mode {
+            Mode::Synchronizer => {
+                let Some(session) = req.extension::<Session>() else {
+                    return Response::new(500)
+                        .with_body("500 Internal Server Error: Csrf::synchronizer requires Sessions");
+                };
+                match session.get::<String>(SESSION_KEY) {
+                    Some(token) => (Some(token.clone()), CsrfToken::issued(token), None),
+                    None => {
+                        let token = CsrfToken {
+                            token: Arc::new(OnceLock::new()),
+                            session: Some(session.clone()),
+                        };
+                        (None, token, None)
+                    }
+                }
+            }
+            #[cfg(feature = "secure-cookies")]
+            Mode::DoubleSubmit(key) => {
+                let context = self.signing_context(&req);
+                let verified = req
+                    .cookie(&self.cookie_name)
+                    .and_then(|value| value.rsplit_once('.'))
+                    .filter(|(token, tag)| is_token(token) && key.verify_tag(&context, token, tag));
+                // Issued up front: clients read the token from the cookie, not the page.
+                match verified {
+                    Some((token, _)) => (Some(token.to_string()), CsrfToken::issued(token.to_string()), None),
+                    None => {
+                        let token = generate_token();
+                        let cookie = format!("{}.{}", token, key.tag(&context, &token));
+                        (None, CsrfToken::issued(token), Some(cookie))
+                    }
+                }
+            }
+        };
+
+        if !is_safe(&req.method) {
+            if !self.origin_allowed(&req) {
+                return Response::new(403).with_body("403 Forbidden: cross-origin request rejected");
+            }
+            // A freshly issued token cannot have been submitted.
+            let valid = expected.is_some_and(|expected| {
+                self.submitted_token(&req)
+                    .is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes()))
+            });
+            if !valid {
+                return Response::new(403).with_body("403 Forbidden: missing or invalid CSRF token");
+            }
+        }
+
+        req.extensions.insert(token);
+        let mut response = next(req);
+        if let Some(value) = issued_cookie {
+            let cookie = Cookie::new(&self.cookie_name, &value)
+                .path("/")
+                .secure(self.secure_cookie)
+                .same_site(SameSite::Strict);
+            response.append_header("Set-Cookie", &cookie.to_string());
+        }
+        response
+    }
+}
+
+fn is_safe(method: &HttpMethod) -> bool {
+    matches!(
+        method,
+        HttpMethod::GET | HttpMethod::HEAD | HttpMethod::OPTIONS | HttpMethod::TRACE
+    )
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(feature = "secure-cookies")]
+fn is_token(value: &str) -> bool {
+    value.len() == 43 && value.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+// `https://example.com:8443/path?q` -> `https://example.com:8443`
+fn origin_of(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    Some(format!("{}://{}", scheme, authority))
+}
@@ -0,0 +1,102 @@
+use std::io::{self, Read};
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+// Transparently decodes request bodies sent with `Content-Encoding: gzip` or `deflate`.
+// The decoded size is capped to defuse zip bombs (413); other encodings get 415.
+pub struct Decompression {
+    max_size: usize,
+}
+
+enum DecodeError {
+    TooLarge,
+    Malformed,
+}
+
+impl Default for Decompression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decompression {
+    // Decoded bodies are limited to 10 MiB unless configured otherwise.
+    pub fn new() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    fn decode(&self, encoding: &str, body: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        match encoding {
+            "gzip" | "x-gzip" => self.read_capped(GzDecoder::new(body)),
+            // `deflate` is meant to be zlib-wrapped, but raw deflate is common in the wild
+            "deflate" => self
+                .read_capped(ZlibDecoder::new(body))
+                .or_else(|_| self.read_capped(DeflateDecoder::new(body))),
+            _ => unreachable!("unsupported encodings are rejected before decoding"),
+        }
+    }
+
+    fn read_capped<R: Read>(&self, reader: R) -> Result<Vec<u8>, DecodeError> {
+        let mut decoded = Vec::new();
+        reader
+            .take(self.max_size as u64 + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|_: io::Error| DecodeError::Malformed)?;
+        if decoded.len() > self.max_size {
+            return Err(DecodeError::TooLarge);
+        }
+        Ok(decoded)
+    }
+}
+
+impl Middleware for Decompression {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        let Some(header) = req.header("Content-Encoding") else {
+            return next(req);
+        };
+
+        // Codings are listed in the order they were applied, so undo them back to front
+        let encodings: Vec<String> = header
+            .split(',')
+            .map(|e| e.trim().to_ascii_lowercase())
+            .filter(|e| !e.is_empty() && e != "identity")
+            .collect();
+
+        if let Some(unsupported) = encodings
+            .iter()
+            .find(|e| !matches!(e.as_str(), "gzip" | "x-gzip" | "deflate"))
+        {
+            return Response::new(415)
+                .with_body(&format!("415 Unsupported Media Type: unsupported Content-Encoding '{}'", unsupported))
+                .with_header("Accept-Encoding", "gzip, deflate");
+        }
+
+        let mut body = std::mem::take(&mut req.body);
+        for encoding in encodings.iter().rev() {
+            body = match self.decode(encoding, &body) {
+                Ok(decoded) => decoded,
+                Err(DecodeError::TooLarge) => {
+                    return Response::new(413).with_body("413 Payload Too Large");
+                }
+                Err(DecodeError::Malformed) => {
+                    return Response::new(400).with_body("400 Bad Request: malformed request body encoding");
+                }
+            };
+        }
+
+        req.headers.remove("Content-Encoding");
+        let _ = req.headers.insert("Content-Length", &body.len().to_string());
+        req.body = body;
+        next(req)
+    }
+}
@@ -0,0 +1,326 @@
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, JwkSet, PublicKeyUse};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use std::{fs, io};
+
+use crate::header::expect_valid;
+use crate::middleware::auth::{quote, Principal};
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+// JWT bearer authentication. Tokens come from `Authorization: Bearer` or, if configured,
+// a cookie. The signature must verify against one of the configured keys, and `exp`,
+// `nbf`, `iss` and `aud` are checked with a clock skew allowance (60s by default).
+// Accepted requests carry the token's `Claims` and a `Principal` named after `sub`.
+//
+//     router.use_global(
+//         JwtAuth::new()
+//             .jwks_file("/etc/gateway/jwks.json")?
+//             .issuer("https://gateway.example.com")
+//             .audience("orders"),
+//     );
+pub struct JwtAuth {
+    keys: Vec<JwtKey>,
+    issuers: Vec<String>,
+    audiences: Vec<String>,
+    leeway: Duration,
+    cookie: Option<String>,
+    realm: Option<String>,
+}
+
+struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+#[derive(Debug)]
+pub enum JwtError {
+    Io(io::Error),
+    InvalidKey(String),
+    InvalidJwks(String),
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtError::Io(e) => write!(f, "failed to read key: {}", e),
+            JwtError::InvalidKey(e) => write!(f, "invalid key: {}", e),
+            JwtError::InvalidJwks(e) => write!(f, "invalid JWKS: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+impl From<io::Error> for JwtError {
+    fn from(e: io::Error) -> Self {
+        JwtError::Io(e)
+    }
+}
+
+// The validated claims of a token.
+//
+//     let tenant = req.extension::<Claims>().and_then(|c| c.get("tenant")).and_then(|t| t.as_str());
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claims(Value);
+
+impl Claims {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.get("sub").and_then(Value::as_str)
+    }
+
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    // Deserializes the claims into an application-defined struct.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.0)
+    }
+}
+
+impl Default for JwtAuth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JwtAuth {
+    // Starts without keys; every token is rejected until at least one is added.
+    pub fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            leeway: Duration::from_secs(60),
+            cookie: None,
+            realm: None,
+        }
+    }
+
+    pub fn hs256(mut self, secret: &[u8]) -> Self {
+        self.keys.push(JwtKey {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            key: DecodingKey::from_secret(secret),
+        });
+        self
+    }
+
+    // An RSA public key in PEM form, for RS256 tokens.
+    pub fn rs256_pem(mut self, pem: &[u8]) -> Result<Self, JwtError> {
+        let key = DecodingKey::from_rsa_pem(pem).map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+        self.keys.push(JwtKey {
+            kid: None,
+            algorithm: Algorithm::RS256,
+            key,
+        });
+        Ok(self)
+    }
+
+    // An Ed25519 public key in PEM form, for EdDSA tokens.
+    pub fn ed25519_pem(mut self, pem: &[u8]) -> Result<Self, JwtError> {
+        let key = DecodingKey::from_ed_pem(pem).map_err(|e| JwtError::InvalidKey(e.to_string()))?;
+        self.keys.push(JwtKey {
+            kid: None,
+            algorithm: Algorithm::EdDSA,
+            key,
+        });
+        Ok(self)
+    }
+
+    pub fn jwks_file<P: AsRef<Path>>(self, path: P) -> Result<Self, JwtError> {
+        self.jwks(&fs::read_to_string(path)?)
+    }
+
+    // Adds the signing keys of a JSON Web Key Set. Keys marked for encryption are skipped;
+    // keys without `alg` get the usual algorithm for their type.
+    pub fn jwks(mut self, json: &str) -> Result<Self, JwtError> {
+        let set: JwkSet = serde_json::from_str(json).map_err(|e| JwtError::InvalidJwks(e.to_string()))?;
+        for jwk in &set.keys {
+            if jwk.common.public_key_use.as_ref().is_some_and(|u| *u != PublicKeyUse::Signature) {
+                continue;
+            }
+            let algorithm = match &jwk.common.key_algorithm {
+                Some(alg) => Algorithm::from_str(&alg.to_string()).ok(),
+                None => match &jwk.algorithm {
+                    AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+                    AlgorithmParameters::OctetKey(_) => Some(Algorithm::HS256),
+                    AlgorithmParameters::OctetKeyPair(_) => Some(Algorithm::EdDSA),
+                    AlgorithmParameters::EllipticCurve(ec) => match ec.curve {
+                        EllipticCurve::P256 => Some(Algorithm::ES256),
+                        EllipticCurve::P384 => Some(Algorithm::ES384),
+                        _ => None,
+                    },
+                },
+            };
+            let Some(algorithm) = algorithm else {
+                let kid = jwk.common.key_id.as_deref().unwrap_or("<no kid>");
+                return Err(JwtError::InvalidJwks(format!("unsupported algorithm for key {}", kid)));
+            };
+            let key = DecodingKey::from_jwk(jwk).map_err(|e| JwtError::InvalidJwks(e.to_string()))?;
+            self.keys.push(JwtKey {
+                kid: jwk.common.key_id.clone(),
+                algorithm,
+                key,
+            });
+        }
+        Ok(self)
+    }
+
+    // Accepted `iss` values; may be called repeatedly. Without any, `iss` is not checked.
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuers.push(issuer.to_string());
+        self
+    }
+
+    // Accepted `aud` values; may be called repeatedly. Without any, tokens carrying an
+    // `aud` claim are accepted regardless of its value.
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audiences.push(audience.to_string());
+        self
+    }
+
+    // Clock skew tolerated when checking `exp` and `nbf`.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    // Also looks for the token in this cookie when there is no `Authorization` header.
+    pub fn cookie(mut self, name: &str) -> Self {
+        self.cookie = Some(name.to_string());
+        self
+    }
+
+    pub fn realm(mut self, realm: &str) -> Self {
+        expect_valid("WWW-Authenticate", realm);
+        self.realm = Some(realm.to_string());
+        self
+    }
+
+    fn token(&self, req: &Request) -> Option<String> {
+        if let Some(auth) = req.headers.authorization() {
+            return auth.bearer().filter(|t| !t.is_empty()).map(str::to_string);
+        }
+        let cookie = self.cookie.as_deref()?;
+        req.headers
+            .cookies()
+            .into_iter()
+            .find(|(name, value)| *name == cookie && !value.is_empty())
+            .map(|(_, value)| value.to_string())
+    }
+
+    // Returns the claims, or the `error_description` to send back.
+    fn verify(&self, token: &str) -> Result<Claims, &'static str> {
+        let header = jsonwebtoken::decode_header(token).map_err(|_| "malformed token")?;
+
+        // Only keys registered for the token's algorithm are tried, which rules out
+        // algorithm confusion such as an RS256 public key being used as an HS256 secret.
+        let candidates = self.keys.iter().filter(|key| {
+            key.algorithm == header.alg
+                && match (&header.kid, &key.kid) {
+                    (Some(wanted), Some(kid)) => wanted == kid,
+                    _ => true,
+                }
+        });
+
+        let mut error = "unknown signing key";
+        for key in candidates {
+            match jsonwebtoken::decode::<Value>(token, &key.key, &self.validation(key.algorithm)) {
+                Ok(data) => return Ok(Claims(data.claims)),
+                Err(e) if matches!(e.kind(), ErrorKind::InvalidSignature) => error = "invalid signature",
+                Err(e) => return Err(describe(e.kind())),
+            }
+        }
+        Err(error)
+    }
+
+    fn validation(&self, algorithm: Algorithm) -> Validation {
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = self.leeway.as_secs();
+        validation.validate_nbf = true;
+        // jsonwebtoken only checks `iss` and `aud` when the token has them, so a
+        // configured issuer or audience must also make the claim mandatory.
+        let mut required = vec!["exp"];
+        if !self.issuers.is_empty() {
+            validation.set_issuer(&self.issuers);
+            required.push("iss");
+        }
+        if self.audiences.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audiences);
+            required.push("aud");
+        }
+        validation.set_required_spec_claims(&required);
+        validation
+    }
+
+    fn challenge(&self, description: Option<&str>) -> Response {
+        let mut params = Vec::new();
+        if let Some(realm) = &self.realm {
+            params.push(format!("realm={}", quote(realm)));
+        }
+        if let Some(description) = description {
+            params.push("error=\"invalid_token\"".to_string());
+            params.push(format!("error_description={}", quote(description)));
+        }
+        let challenge = if params.is_empty() {
+            "Bearer".to_string()
+        } else {
+            format!("Bearer {}", params.join(", "))
+        };
+        Response::new(401)
+            .with_body("401 Unauthorized")
+            .with_header("WWW-Authenticate", &challenge)
+    }
+
+    // Fails with the `error_description` for the challenge.
+    fn authenticate(&self, req: &Request) -> Result<Claims, Option<&'static str>> {
+        let token = self.token(req).ok_or(None)?;
+        self.verify(&token).map_err(Some)
+    }
+}
+
+fn describe(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::ExpiredSignature => "token expired",
+        ErrorKind::ImmatureSignature => "token not yet valid",
+        ErrorKind::InvalidIssuer => "invalid issuer",
+        ErrorKind::InvalidAudience => "invalid audience",
+        ErrorKind::MissingRequiredClaim(_) => "missing required claim",
+        ErrorKind::InvalidSignature => "invalid signature",
+        ErrorKind::InvalidAlgorithm => "unsupported algorithm",
+        _ => "malformed token",
+    }
+}
+
+impl Middleware for JwtAuth {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        match self.authenticate(&req) {
+            Ok(claims) => {
+                let subject = claims.subject().unwrap_or_default().to_string();
+                req.extensions.insert(Principal::new(&subject, "Bearer"));
+                req.extensions.insert(claims);
+                next(req)
+            }
+            Err(description) => self.challenge(description),
+        }
+    }
+
+    fn on_expect_continue(&self, req: &Request) -> Option<Response> {
+        self.authenticate(req).err().map(|description| self.challenge(description))
+    }
+}
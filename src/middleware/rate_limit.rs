@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+type KeyFn = dyn Fn(&Request) -> Option<String> + Send + Sync;
+
+// Rate limiting keyed by client IP (default), a header such as an API key, or a custom
+// function. Rejected requests get 429 with `Retry-After`; every limited response carries
+// `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`.
+//
+//     router.use_global(RateLimit::token_bucket(100, Duration::from_secs(60)).key_by_header("X-Api-Key"));
+pub struct RateLimit {
+    algorithm: Algorithm,
+    key: RateLimitKey,
+    state: Mutex<HashMap<String, LimiterState>>,
+    calls: AtomicU64,
+}
+
+#[derive(Clone)]
+pub enum RateLimitKey {
+    ClientIp,
+    // Falls back to the client IP when the header is missing.
+    Header(String),
+    // Requests for which the function returns `None` are not limited.
+    Custom(Arc<KeyFn>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Algorithm {
+    // `capacity` tokens, refilled continuously over `period`.
+    TokenBucket { capacity: u32, period: Duration },
+    // At most `limit` requests in any `window`, using the weighted two-window approximation.
+    SlidingWindow { limit: u32, window: Duration },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LimiterState {
+    Bucket { tokens: f64, updated: Instant },
+    Window { start: Instant, current: u32, previous: u32 },
+}
+
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+    retry_after: Duration,
+}
+
+// How many calls pass between sweeps of idle limiter entries.
+const SWEEP_INTERVAL: u64 = 1024;
+
+impl RateLimit {
+    pub fn token_bucket(capacity: u32, period: Duration) -> Self {
+        Self::with_algorithm(Algorithm::TokenBucket {
+            capacity: capacity.max(1),
+            period,
+        })
+    }
+
+    pub fn sliding_window(limit: u32, window: Duration) -> Self {
+        Self::with_algorithm(Algorithm::SlidingWindow {
+            limit: limit.max(1),
+            window,
+        })
+    }
+
+    fn with_algorithm(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            key: RateLimitKey::ClientIp,
+            state: Mutex::new(HashMap::new()),
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    pub fn key_by_ip(mut self) -> Self {
+        self.key = RateLimitKey::ClientIp;
+        self
+    }
+
+    pub fn key_by_header(mut self, header: &str) -> Self {
+        self.key = RateLimitKey::Header(header.to_string());
+        self
+    }
+
+    pub fn key_by<F>(mut self, key_fn: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = RateLimitKey::Custom(Arc::new(key_fn));
+        self
+    }
+
+    fn key_for(&self, req: &Request) -> Option<String> {
+        let client_ip = || {
+            let ip = req.remote_addr.map(|addr| addr.ip().to_string());
+            Some(format!("ip:{}", ip.unwrap_or_else(|| "unknown".to_string())))
+        };
+        match &self.key {
+            RateLimitKey::ClientIp => client_ip(),
+            RateLimitKey::Header(name) => match req.header(name) {
+                Some(value) => Some(format!("header:{}", value)),
+                None => client_ip(),
+            },
+            RateLimitKey::Custom(key_fn) => key_fn(req),
+        }
+    }
+
+    fn check(&self, key: String, now: Instant) -> Decision {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if self.calls.fetch_add(1, Ordering::Relaxed) % SWEEP_INTERVAL == SWEEP_INTERVAL - 1 {
+            let idle_after = match self.algorithm {
+                Algorithm::TokenBucket { period, .. } => period,
+                Algorithm::SlidingWindow { window, .. } => window * 2,
+            };
+            state.retain(|_, entry| match entry {
+                LimiterState::Bucket { updated, .. } => now.duration_since(*updated) < idle_after,
+                LimiterState::Window { start, .. } => now.duration_since(*start) < idle_after,
+            });
+        }
+
+        match self.algorithm {
+            Algorithm::TokenBucket { capacity, period } => {
+                let rate = capacity as f64 / period.as_secs_f64().max(f64::EPSILON);
+                let entry = state.entry(key).or_insert(LimiterState::Bucket {
+                    tokens: capacity as f64,
+                    updated: now,
+                });
+                let LimiterState::Bucket { tokens, updated } = entry else {
+                    unreachable!("token bucket state")
+                };
+
+                *tokens = (*tokens + now.duration_since(*updated).as_secs_f64() * rate).min(capacity as f64);
+                *updated = now;
+
+                let allowed = *tokens >= 1.0;
+                if allowed {
+                    *tokens -= 1.0;
+                }
+                let missing_for_one = (1.0 - *tokens).max(0.0);
+                Decision {
+                    allowed,
+                    limit: capacity,
+                    remaining: tokens.floor() as u32,
+                    reset: Duration::from_secs_f64((capacity as f64 - *tokens) / rate),
+                    retry_after: Duration::from_secs_f64(missing_for_one / rate),
+                }
+            }
+            Algorithm::SlidingWindow { limit, window } => {
+                let entry = state.entry(key).or_insert(LimiterState::Window {
+                    start: now,
+                    current: 0,
+                    previous: 0,
+                });
+                let LimiterState::Window { start, current, previous } = entry else {
+                    unreachable!("sliding window state")
+                };
+
+                let elapsed = now.duration_since(*start);
+                if elapsed >= window * 2 {
+                    *start = now;
+                    *previous = 0;
+                    *current = 0;
+                } else if elapsed >= window {
+                    *start += window;
+                    *previous = *current;
+                    *current = 0;
+                }
+
+                let into_window = now.duration_since(*start).as_secs_f64() / window.as_secs_f64().max(f64::EPSILON);
+                let weighted = *previous as f64 * (1.0 - into_window) + *current as f64;
+                let allowed = weighted + 1.0 <= limit as f64;
+                if allowed {
+                    *current += 1;
+                }
+
+                let used = *previous as f64 * (1.0 - into_window) + *current as f64;
+                let until_next_window = window.saturating_sub(now.duration_since(*start));
+                Decision {
+                    allowed,
+                    limit,
+                    remaining: (limit as f64 - used).max(0.0).floor() as u32,
+                    reset: until_next_window,
+                    retry_after: until_next_window,
+                }
+            }
+        }
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, req: Request, next: NextFn) -> Response {
+        let Some(key) = self.key_for(&req) else {
+            return next(req);
+        };
+
+        let decision = self.check(key, Instant::now());
+        let mut response = if decision.allowed {
+            next(req)
+        } else {
+            let retry_after = decision.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            Response::new(429)
+                .with_body("429 Too Many Requests")
+                .with_header("Retry-After", &retry_after.to_string())
+        };
+
+        response.set_header("RateLimit-Limit", &decision.limit.to_string());
+        response.set_header("RateLimit-Remaining", &decision.remaining.to_string());
+        response.set_header(
+            "RateLimit-Reset",
+            &(decision.reset.as_secs_f64().ceil() as u64).to_string(),
+        );
+        response
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::header::expect_valid;
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+pub use crate::request::RequestId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Uuid,
+    Ulid,
+}
+
+// Assigns every request an ID: the incoming `X-Request-Id` if it looks sane (so IDs from
+// a proxy or calling service carry through), otherwise a fresh UUIDv4 or ULID. The ID is
+// echoed in the response header. Register it first so every other middleware sees it.
+//
+//     router.use_global(RequestIds::ulid());
+//
+// Responses the server makes itself (handler timeouts, overload, panics) don't pass
+// through the middleware. Giving it to `Server::with_request_ids` instead assigns the ID
+// before the router runs, so those responses and their log lines carry it too; a
+// `RequestIds` middleware in the router then keeps that ID.
+#[derive(Debug, Clone)]
+pub struct RequestIds {
+    format: Format,
+    header: String,
+    trust_incoming: bool,
+}
+
+impl Default for RequestIds {
+    fn default() -> Self {
+        Self::uuid()
+    }
+}
+
+impl RequestIds {
+    pub fn uuid() -> Self {
+        Self::with_format(Format::Uuid)
+    }
+
+    // Lexicographically sortable by creation time.
+    pub fn ulid() -> Self {
+        Self::with_format(Format::Ulid)
+    }
+
+    fn with_format(format: Format) -> Self {
+        Self {
+            format,
+            header: "X-Request-Id".to_string(),
+            trust_incoming: true,
+        }
+    }
+
+    // Panics if `name` is not a valid header name.
+    pub fn header(mut self, name: &str) -> Self {
+        expect_valid(name, "");
+        self.header = name.to_string();
+        self
+    }
+
+    // Whether to reuse an ID sent by the client. Turn off when clients are untrusted and
+    // IDs must be unique.
+    pub fn trust_incoming(mut self, trust: bool) -> Self {
+        self.trust_incoming = trust;
+        self
+    }
+
+    // The request's ID, assigned now unless the server or an earlier `RequestIds` did.
+    pub(crate) fn assign(&self, req: &mut Request) -> RequestId {
+        if let Some(id) = req.extension::<RequestId>() {
+            return id.clone();
+        }
+        let id = req
+            .header(&self.header)
+            .filter(|id| self.trust_incoming && is_acceptable(id))
+            .map(str::to_string)
+            .unwrap_or_else(|| self.generate());
+        let id = RequestId(id);
+        req.extensions.insert(id.clone());
+        id
+    }
+
+    // Echoes `id` in the response header and hands it to the logger.
+    pub(crate) fn stamp(&self, response: &mut Response, id: &RequestId) {
+        response.set_header(&self.header, id.as_str());
+        response.extensions.insert(id.clone());
+    }
+
+    fn generate(&self) -> String {
+        match self.format {
+            Format::Uuid => generate_uuid(),
+            Format::Ulid => generate_ulid(),
+        }
+    }
+}
+
+impl Middleware for RequestIds {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        let id = self.assign(&mut req);
+        let mut response = next(req);
+        self.stamp(&mut response, &id);
+        response
+    }
+}
+
+// Incoming IDs end up in logs, so only short, printable, space-free values are reused.
+fn is_acceptable(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.:/+=".contains(&b))
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    bytes
+}
+
+fn generate_uuid() -> String {
+    let mut bytes = random_bytes::<16>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+// 48-bit millisecond timestamp followed by 80 random bits, in Crockford base32.
+fn generate_ulid() -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let random = u128::from_be_bytes({
+        let mut bytes = [0u8; 16];
+        bytes[6..].copy_from_slice(&random_bytes::<10>());
+        bytes
+    });
+    let value = ((millis & 0xffff_ffff_ffff) << 80) | random;
+    (0..26)
+        .rev()
+        .map(|i| ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
@@ -0,0 +1,195 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::time::Duration;
+
+use crate::header::expect_valid;
+use crate::middleware::{Middleware, NextFn};
+use crate::{Request, Response};
+
+// The per-request nonce substituted for `{nonce}` in the Content-Security-Policy:
+//
+//     let nonce = req.extension::<CspNonce>().unwrap();
+//     format!("<script nonce=\"{}\">...</script>", nonce.as_str())
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(String);
+
+impl CspNonce {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+// Adds security-related response headers. Defaults:
+//
+//     Strict-Transport-Security: max-age=31536000; includeSubDomains   (HTTPS requests only)
+//     X-Content-Type-Options: nosniff
+//     X-Frame-Options: DENY
+//     Referrer-Policy: strict-origin-when-cross-origin
+//     Cross-Origin-Opener-Policy: same-origin
+//     Cross-Origin-Resource-Policy: same-origin
+//
+// Headers the handler already set are left alone, so single routes can override them.
+// Values are validated by the builder methods, which panic on control characters.
+//
+//     router.use_global(
+//         SecurityHeaders::new()
+//             .content_security_policy("default-src 'self'; script-src 'self' 'nonce-{nonce}'")
+//             .frame_options("SAMEORIGIN")
+//             .without("Cross-Origin-Resource-Policy"),
+//     );
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    hsts: Option<String>,
+    csp: Option<String>,
+    csp_report_only: bool,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecurityHeaders {
+    pub fn new() -> Self {
+        let defaults = [
+            ("X-Content-Type-Options", "nosniff"),
+            ("X-Frame-Options", "DENY"),
+            ("Referrer-Policy", "strict-origin-when-cross-origin"),
+            ("Cross-Origin-Opener-Policy", "same-origin"),
+            ("Cross-Origin-Resource-Policy", "same-origin"),
+        ];
+        Self {
+            hsts: Some("max-age=31536000; includeSubDomains".to_string()),
+            csp: None,
+            csp_report_only: false,
+            headers: defaults.iter().map(|(name, value)| (*name, value.to_string())).collect(),
+        }
+    }
+
+    // Browsers ignore HSTS received over plain HTTP, so it is only sent on TLS requests.
+    pub fn hsts(mut self, max_age: Duration, include_subdomains: bool, preload: bool) -> Self {
+        let mut value = format!("max-age={}", max_age.as_secs());
+        if include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if preload {
+            value.push_str("; preload");
+        }
+        self.hsts = Some(value);
+        self
+    }
+
+    // `{nonce}` in the policy is replaced by a fresh random nonce for every request, which
+    // handlers read from the `CspNonce` extension.
+    pub fn content_security_policy(mut self, policy: &str) -> Self {
+        expect_valid("Content-Security-Policy", policy);
+        self.csp = Some(policy.to_string());
+        self
+    }
+
+    // Sends the policy as `Content-Security-Policy-Report-Only` to trial it.
+    pub fn csp_report_only(mut self, report_only: bool) -> Self {
+        self.csp_report_only = report_only;
+        self
+    }
+
+    pub fn content_type_options(self, value: &str) -> Self {
+        self.set("X-Content-Type-Options", value)
+    }
+
+    // `DENY` or `SAMEORIGIN`.
+    pub fn frame_options(self, value: &str) -> Self {
+        self.set("X-Frame-Options", value)
+    }
+
+    pub fn referrer_policy(self, value: &str) -> Self {
+        self.set("Referrer-Policy", value)
+    }
+
+    // E.g. `camera=(), geolocation=(self)`.
+    pub fn permissions_policy(self, value: &str) -> Self {
+        self.set("Permissions-Policy", value)
+    }
+
+    pub fn cross_origin_opener_policy(self, value: &str) -> Self {
+        self.set("Cross-Origin-Opener-Policy", value)
+    }
+
+    pub fn cross_origin_embedder_policy(self, value: &str) -> Self {
+        self.set("Cross-Origin-Embedder-Policy", value)
+    }
+
+    pub fn cross_origin_resource_policy(self, value: &str) -> Self {
+        self.set("Cross-Origin-Resource-Policy", value)
+    }
+
+    // Stops sending a header, including `Strict-Transport-Security` and the CSP, which
+    // either of its header names switches off.
+    pub fn without(mut self, name: &str) -> Self {
+        if name.eq_ignore_ascii_case("Strict-Transport-Security") {
+            self.hsts = None;
+        } else if name.eq_ignore_ascii_case("Content-Security-Policy")
+            || name.eq_ignore_ascii_case("Content-Security-Policy-Report-Only")
+        {
+            self.csp = None;
+        }
+        self.headers.retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+        self
+    }
+
+    fn set(mut self, name: &'static str, value: &str) -> Self {
+        expect_valid(name, value);
+        match self.headers.iter_mut().find(|(header, _)| *header == name) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.headers.push((name, value.to_string())),
+        }
+        self
+    }
+}
+
+impl Middleware for SecurityHeaders {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        let is_https = req.scheme() == "https";
+
+        let csp = self.csp.as_ref().map(|policy| {
+            if !policy.contains("{nonce}") {
+                return policy.clone();
+            }
+            let nonce = generate_nonce();
+            let policy = policy.replace("{nonce}", &nonce);
+            req.extensions.insert(CspNonce(nonce));
+            policy
+        });
+
+        let mut response = next(req);
+        let mut add = |name: &str, value: &str| {
+            if !response.headers.contains(name) {
+                response.set_header(name, value);
+            }
+        };
+
+        if let (Some(hsts), true) = (&self.hsts, is_https) {
+            add("Strict-Transport-Security", hsts);
+        }
+        if let Some(csp) = &csp {
+            let name = if self.csp_report_only {
+                "Content-Security-Policy-Report-Only"
+            } else {
+                "Content-Security-Policy"
+            };
+            add(name, csp);
+        }
+        for (name, value) in &self.headers {
+            add(name, value);
+        }
+        response
+    }
+}
+
+fn generate_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    STANDARD.encode(bytes)
+}
@@ -0,0 +1,457 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+use crate::cookie::{Cookie, SameSite};
+use crate::middleware::{Middleware, NextFn};
+use crate::path_pattern::percent_decode;
+use crate::{Request, Response};
+
+pub type SessionData = HashMap<String, String>;
+
+// Where session data lives between requests. `load` must not return expired sessions.
+pub trait SessionStore: Send + Sync {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>>;
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()>;
+    fn delete(&self, id: &str) -> io::Result<()>;
+    // Extends the TTL of a session whose data did not change. Stores that can do this
+    // without rewriting the data should override it.
+    fn touch(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        self.save(id, data, ttl)
+    }
+    // Drops expired sessions; called periodically by `Sessions` on a background thread.
+    fn cleanup(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Keeps sessions in process memory; they are lost on restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, (SessionData, SystemTime)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions
+            .get(id)
+            .filter(|(_, expires)| *expires > SystemTime::now())
+            .map(|(data, _)| data.clone()))
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        let expires = SystemTime::now() + ttl;
+        self.sessions.lock().unwrap().insert(id.to_string(), (data.clone(), expires));
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> io::Result<()> {
+        self.sessions.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn touch(&self, id: &str, _data: &SessionData, ttl: Duration) -> io::Result<()> {
+        if let Some((_, expires)) = self.sessions.lock().unwrap().get_mut(id) {
+            *expires = SystemTime::now() + ttl;
+        }
+        Ok(())
+    }
+
+    fn cleanup(&self) -> io::Result<()> {
+        let now = SystemTime::now();
+        self.sessions.lock().unwrap().retain(|_, (_, expires)| *expires > now);
+        Ok(())
+    }
+}
+
+// One file per session in `dir`, so sessions survive restarts. The first line holds the
+// expiry as Unix seconds, followed by one percent-encoded `key=value` per line.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    // Creates `dir` if it does not exist.
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.session", id))
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let contents = match fs::read_to_string(self.path(id)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut lines = contents.lines();
+        let expires: u64 = lines.next().and_then(|line| line.parse().ok()).unwrap_or(0);
+        if expires <= unix_now() {
+            return Ok(None);
+        }
+        let data = lines
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+            .collect();
+        Ok(Some(data))
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        let mut contents = format!("{}\n", unix_now() + ttl.as_secs());
+        for (key, value) in data {
+            contents.push_str(&format!("{}={}\n", escape(key), escape(value)));
+        }
+        // Write then rename so a concurrent `load` never sees a partial file. The temporary
+        // name is unique, so concurrent saves of one session don't write the same file.
+        let mut suffix = [0u8; 8];
+        getrandom::getrandom(&mut suffix).expect("OS random number generator unavailable");
+        let tmp = self.dir.join(format!("{}.{}.tmp", id, URL_SAFE_NO_PAD.encode(suffix)));
+        if let Err(e) = fs::write(&tmp, contents) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        fs::rename(&tmp, self.path(id)).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+    }
+
+    // Rewrites the file only once a tenth of the TTL has passed since the last write, so a
+    // busy session isn't written on every request.
+    fn touch(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        let refresh_at = (unix_now() + ttl.as_secs()).saturating_sub(ttl.as_secs() / 10);
+        match read_expiry(&self.path(id)) {
+            Some(expires) if expires >= refresh_at => Ok(()),
+            _ => self.save(id, data, ttl),
+        }
+    }
+
+    fn delete(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn cleanup(&self) -> io::Result<()> {
+        let now = unix_now();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "session") {
+                continue;
+            }
+            if read_expiry(&path).is_none_or(|expires| expires <= now) {
+                let _ = fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_expiry(path: &Path) -> Option<u64> {
+    let contents = fs::read_to_string(path).ok()?;
+    contents.lines().next()?.parse().ok()
+}
+
+// The current request's session, available to handlers as an extension:
+//
+//     let session = req.extension::<Session>().unwrap();
+//     let visits: u32 = session.get("visits").unwrap_or(0);
+//     session.insert("visits", visits + 1);
+//
+// Values are stored as strings and converted with `FromStr`/`ToString`.
+#[derive(Clone)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+}
+
+#[derive(Default)]
+struct SessionState {
+    // `None` until the session is first stored.
+    id: Option<String>,
+    data: SessionData,
+    modified: bool,
+    renew: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    fn new(id: Option<String>, data: SessionData) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SessionState {
+                id,
+                data,
+                ..SessionState::default()
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap()
+    }
+
+    // `None` for a new session that has not been stored yet.
+    pub fn id(&self) -> Option<String> {
+        self.state().id.clone()
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.state().data.get(key)?.parse().ok()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.state().data.contains_key(key)
+    }
+
+    pub fn insert<T: ToString>(&self, key: &str, value: T) {
+        let mut state = self.state();
+        state.data.insert(key.to_string(), value.to_string());
+        state.modified = true;
+    }
+
+    pub fn remove(&self, key: &str) -> Option<String> {
+        let mut state = self.state();
+        state.modified = true;
+        state.data.remove(key)
+    }
+
+    // Issues a new session ID while keeping the data. Call it whenever the privilege level
+    // changes (login, sudo mode) to prevent session fixation.
+    pub fn renew(&self) {
+        let mut state = self.state();
+        state.renew = true;
+        state.modified = true;
+    }
+
+    // Deletes the session from the store and the client, e.g. on logout.
+    pub fn destroy(&self) {
+        let mut state = self.state();
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+// How many requests pass between calls to `SessionStore::cleanup`.
+const CLEANUP_INTERVAL: u64 = 1024;
+
+// Cookie-based sessions. The cookie holds only a random 256-bit ID; data stays in the
+// store. A cookie is issued once the session has data, so anonymous visitors that never
+// touch the session do not create one. Each request extends the session's idle TTL.
+//
+//     router.use_global(Sessions::new(MemoryStore::new()).ttl(Duration::from_secs(1800)));
+pub struct Sessions {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+    same_site: SameSite,
+    domain: Option<String>,
+    calls: AtomicU64,
+    cleaning: Arc<AtomicBool>,
+}
+
+impl Sessions {
+    pub fn new<S: SessionStore + 'static>(store: S) -> Self {
+        Self::with_store(Arc::new(store))
+    }
+
+    // Shares a store with other code, e.g. to revoke sessions from an admin endpoint.
+    pub fn with_store(store: Arc<dyn SessionStore>) -> Self {
+        Self {
+            store,
+            cookie_name: "session".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: true,
+            same_site: SameSite::Lax,
+            domain: None,
+            calls: AtomicU64::new(0),
+            cleaning: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    // Idle time after which a session expires. Defaults to 24 hours.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // Whether the cookie is `Secure`. On by default; turn off only for plain-HTTP development.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    fn cookie(&self, value: &str) -> Cookie {
+        let mut cookie = Cookie::new(&self.cookie_name, value)
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site);
+        if let Some(domain) = &self.domain {
+            cookie = cookie.domain(domain);
+        }
+        cookie
+    }
+
+    fn removal_cookie(&self) -> Cookie {
+        let mut cookie = Cookie::removal(&self.cookie_name).path("/");
+        if let Some(domain) = &self.domain {
+            cookie = cookie.domain(domain);
+        }
+        cookie
+    }
+
+    fn load(&self, req: &Request) -> io::Result<Session> {
+        let Some(id) = req.cookie(&self.cookie_name).filter(|id| is_session_id(id)) else {
+            return Ok(Session::new(None, SessionData::new()));
+        };
+        Ok(match self.store.load(id)? {
+            Some(data) => Session::new(Some(id.to_string()), data),
+            None => Session::new(None, SessionData::new()),
+        })
+    }
+
+    // Persists the session after the handler ran and sets or clears the cookie.
+    fn commit(&self, session: &Session, had_cookie: bool, response: &mut Response) -> io::Result<()> {
+        let mut state = session.state();
+
+        if state.destroyed {
+            if let Some(id) = state.id.take() {
+                self.store.delete(&id)?;
+            }
+            if had_cookie {
+                response.append_header("Set-Cookie", &self.removal_cookie().to_string());
+            }
+            return Ok(());
+        }
+
+        if state.renew {
+            if let Some(old_id) = state.id.take() {
+                self.store.delete(&old_id)?;
+            }
+        }
+
+        match state.id.clone() {
+            Some(id) if state.modified => self.store.save(&id, &state.data, self.ttl),
+            Some(id) => self.store.touch(&id, &state.data, self.ttl),
+            None if state.modified => {
+                let id = generate_session_id();
+                self.store.save(&id, &state.data, self.ttl)?;
+                response.append_header("Set-Cookie", &self.cookie(&id).to_string());
+                state.id = Some(id);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Runs `SessionStore::cleanup` on its own thread, as scanning a `FileStore` directory
+    // can take a while. Skipped while the previous run is still going.
+    fn spawn_cleanup(&self) {
+        if self.cleaning.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let store = Arc::clone(&self.store);
+        let cleaning = Arc::clone(&self.cleaning);
+        let spawned = std::thread::Builder::new()
+            .name("session-cleanup".to_string())
+            .spawn(move || {
+                let _ = store.cleanup();
+                cleaning.store(false, Ordering::Release);
+            });
+        if spawned.is_err() {
+            self.cleaning.store(false, Ordering::Release);
+        }
+    }
+}
+
+impl Middleware for Sessions {
+    fn handle(&self, mut req: Request, next: NextFn) -> Response {
+        if self.calls.fetch_add(1, Ordering::Relaxed) % CLEANUP_INTERVAL == CLEANUP_INTERVAL - 1 {
+            self.spawn_cleanup();
+        }
+
+        let had_cookie = req.cookie(&self.cookie_name).is_some();
+        let session = match self.load(&req) {
+            Ok(session) => session,
+            Err(_) => return Response::new(500).with_body("500 Internal Server Error"),
+        };
+        req.extensions.insert(session.clone());
+
+        let mut response = next(req);
+        match self.commit(&session, had_cookie, &mut response) {
+            Ok(()) => response,
+            // Failing silently would lose logins and logouts.
+            Err(_) => Response::new(500).with_body("500 Internal Server Error"),
+        }
+    }
+}
+
+fn generate_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Only IDs we could have issued are looked up, which also keeps `FileStore` paths safe.
+fn is_session_id(id: &str) -> bool {
+    id.len() == 43 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '%' | '=' | '\n' | '\r' => escaped.push_str(&format!("%{:02X}", c as u8)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,538 @@
+// `multipart/form-data` parsing (RFC 7578). Parts are read one at a time from the
+// underlying reader through a small lookahead buffer, so a file part can be copied to
+// disk without ever being held in memory as a whole.
+//
+// The server reads the body before the handler runs. Uploads larger than
+// `Limits::spool_multipart_over` (1 MiB by default) go to a temp file as they arrive, and
+// `Request::multipart` parses them from there; smaller ones are parsed from memory.
+//
+//     fn upload(req: Request) -> Result<Response, MultipartError> {
+//         let mut multipart = req.multipart()?.max_part_size(Some(50 * 1024 * 1024));
+//         while let Some(mut part) = multipart.next_part()? {
+//             if part.is_file() {
+//                 part.save_temp()?.persist("uploads/avatar.png").map_err(MultipartError::Io)?;
+//             } else {
+//                 println!("{} = {}", part.name(), part.read_text()?);
+//             }
+//         }
+//         Ok(Response::new(204))
+//     }
+//
+//     router
+//         .post("/uploads", |req| upload(req).unwrap_or_else(Response::from))
+//         .max_body_size(100 * 1024 * 1024);
+//
+// Any other `Read` can be parsed with `Multipart::new`.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::header::{parse_params, HeaderMap};
+use crate::Response;
+
+// Upper bound for the headers of a single part.
+const MAX_PART_HEAD_SIZE: usize = 8 * 1024;
+const READ_CHUNK: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // Before the first boundary.
+    Preamble,
+    // Right after a boundary, where part headers start.
+    Headers,
+    Body,
+    Done,
+}
+
+pub struct Multipart<R> {
+    reader: R,
+    // `\r\n--boundary`; the buffer starts out with `\r\n` so that the first boundary
+    // matches as well.
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    start: usize,
+    eof: bool,
+    state: State,
+    temp_dir: PathBuf,
+    max_parts: Option<usize>,
+    max_part_size: Option<u64>,
+    max_total_size: Option<u64>,
+    parts: usize,
+    part_size: u64,
+    total_size: u64,
+}
+
+impl<R: Read> Multipart<R> {
+    // Allows 100 parts of any size by default.
+    pub fn new(reader: R, boundary: &str) -> Self {
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+        Self {
+            reader,
+            delimiter,
+            buffer: b"\r\n".to_vec(),
+            start: 0,
+            eof: false,
+            state: State::Preamble,
+            temp_dir: std::env::temp_dir(),
+            max_parts: Some(100),
+            max_part_size: None,
+            max_total_size: None,
+            parts: 0,
+            part_size: 0,
+            total_size: 0,
+        }
+    }
+
+    pub fn max_parts(mut self, max: Option<usize>) -> Self {
+        self.max_parts = max;
+        self
+    }
+
+    // In bytes, for the content of each part.
+    pub fn max_part_size(mut self, max: Option<u64>) -> Self {
+        self.max_part_size = max;
+        self
+    }
+
+    // In bytes, for everything read, including boundaries and part headers.
+    pub fn max_total_size(mut self, max: Option<u64>) -> Self {
+        self.max_total_size = max;
+        self
+    }
+
+    // Where `Part::save_temp` creates files; the system temp directory by default.
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = dir.into();
+        self
+    }
+
+    // The next part, skipping whatever the previous one left unread. `None` after the
+    // closing boundary.
+    pub fn next_part(&mut self) -> Result<Option<Part<'_, R>>, MultipartError> {
+        let mut scratch = [0u8; READ_CHUNK];
+        while matches!(self.state, State::Preamble | State::Body) {
+            self.read_data(&mut scratch)?;
+        }
+        if self.state == State::Done {
+            return Ok(None);
+        }
+
+        if let Some(max) = self.max_parts {
+            if self.parts >= max {
+                return Err(MultipartError::TooManyParts(max));
+            }
+        }
+        self.parts += 1;
+
+        let headers = self.read_headers()?;
+        let disposition = headers
+            .get("Content-Disposition")
+            .ok_or(MultipartError::Malformed("part without Content-Disposition"))?;
+        let (kind, params) = parse_params(disposition);
+        if !kind.eq_ignore_ascii_case("form-data") {
+            return Err(MultipartError::Malformed("part is not form-data"));
+        }
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(param, _)| param == name)
+                .map(|(_, value)| value.clone())
+        };
+        let name = param("name").ok_or(MultipartError::Malformed("part without a name"))?;
+        let filename = param("filename*")
+            .as_deref()
+            .and_then(decode_ext_value)
+            .or_else(|| param("filename"));
+        let content_type = headers.get("Content-Type").map(str::to_string);
+
+        self.state = State::Body;
+        self.part_size = 0;
+        Ok(Some(Part {
+            multipart: self,
+            headers,
+            name,
+            filename,
+            content_type,
+        }))
+    }
+
+    fn pending(&self) -> &[u8] {
+        &self.buffer[self.start..]
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.start += n;
+    }
+
+    // Reads more input into the buffer. Returns false at the end of the input.
+    fn fill(&mut self) -> Result<bool, MultipartError> {
+        if self.eof {
+            return Ok(false);
+        }
+        if self.start > 0 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+
+        let len = self.buffer.len();
+        self.buffer.resize(len + READ_CHUNK, 0);
+        let n = loop {
+            match self.reader.read(&mut self.buffer[len..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buffer.truncate(len);
+                    return Err(MultipartError::Io(e));
+                }
+            }
+        };
+        self.buffer.truncate(len + n);
+
+        self.total_size += n as u64;
+        if let Some(max) = self.max_total_size {
+            if self.total_size > max {
+                return Err(MultipartError::TooLarge(max));
+            }
+        }
+        self.eof = n == 0;
+        Ok(n > 0)
+    }
+
+    // Copies data up to the next delimiter into `out`. Returns 0 once the delimiter is
+    // reached, after moving on to the next part's headers or to the end.
+    fn read_data(&mut self, out: &mut [u8]) -> Result<usize, MultipartError> {
+        if !matches!(self.state, State::Preamble | State::Body) || out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let pending = self.pending();
+            // Bytes that cannot be the start of a delimiter are safe to hand out.
+            let (available, at_delimiter) = match find(pending, &self.delimiter) {
+                Some(i) => (i, i == 0),
+                None => (pending.len().saturating_sub(self.delimiter.len() - 1), false),
+            };
+
+            if available > 0 {
+                let n = available.min(out.len());
+                out[..n].copy_from_slice(&pending[..n]);
+                self.consume(n);
+                if self.state == State::Body {
+                    self.part_size += n as u64;
+                    if let Some(max) = self.max_part_size {
+                        if self.part_size > max {
+                            return Err(MultipartError::PartTooLarge(max));
+                        }
+                    }
+                }
+                return Ok(n);
+            }
+            if at_delimiter {
+                self.consume(self.delimiter.len());
+                self.after_delimiter()?;
+                return Ok(0);
+            }
+            if !self.fill()? {
+                return Err(MultipartError::Malformed("unexpected end of multipart body"));
+            }
+        }
+    }
+
+    // A delimiter is followed by `--` for the last one, or by optional whitespace and a
+    // line break.
+    fn after_delimiter(&mut self) -> Result<(), MultipartError> {
+        loop {
+            let pending = self.pending();
+            if pending.starts_with(b"--") {
+                // Whatever follows is an epilogue and ignored.
+                self.state = State::Done;
+                return Ok(());
+            }
+            let padding = pending.iter().take_while(|b| matches!(b, b' ' | b'\t')).count();
+            let rest = &pending[padding..];
+            if rest.starts_with(b"\r\n") {
+                self.consume(padding + 2);
+                self.state = State::Headers;
+                return Ok(());
+            }
+            // Either ending could still be completed by more input.
+            let incomplete = rest.is_empty() || rest == b"\r" || pending == b"-";
+            if !incomplete || padding > MAX_PART_HEAD_SIZE {
+                return Err(MultipartError::Malformed("invalid boundary line"));
+            }
+            if !self.fill()? {
+                return Err(MultipartError::Malformed("unexpected end of multipart body"));
+            }
+        }
+    }
+
+    fn read_headers(&mut self) -> Result<HeaderMap, MultipartError> {
+        loop {
+            let pending = self.pending();
+            // A part without any headers starts with the blank line.
+            let end = if pending.starts_with(b"\r\n") {
+                Some((0, 2))
+            } else {
+                find(pending, b"\r\n\r\n").map(|i| (i, i + 4))
+            };
+
+            if let Some((head_end, consumed)) = end {
+                let head = String::from_utf8_lossy(&pending[..head_end]).into_owned();
+                self.consume(consumed);
+                let mut headers = HeaderMap::new();
+                // Parsed as strictly as request headers; `head` is empty for a part
+                // without headers.
+                for line in head.split("\r\n").filter(|line| !line.is_empty()) {
+                    let (name, value) = line
+                        .split_once(':')
+                        .ok_or(MultipartError::Malformed("invalid part header"))?;
+                    headers
+                        .append(name, value.trim())
+                        .map_err(|_| MultipartError::Malformed("invalid part header"))?;
+                }
+                return Ok(headers);
+            }
+            if pending.len() > MAX_PART_HEAD_SIZE {
+                return Err(MultipartError::Malformed("part headers too large"));
+            }
+            if !self.fill()? {
+                return Err(MultipartError::Malformed("unexpected end of multipart body"));
+            }
+        }
+    }
+}
+
+// One part of a multipart body. Reading it (through `Read` or the helpers) yields its
+// content and stops at the next boundary.
+pub struct Part<'a, R> {
+    multipart: &'a mut Multipart<R>,
+    headers: HeaderMap,
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+}
+
+impl<R: Read> Part<'_, R> {
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    // The form field name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The client's file name, for file parts. Untrusted: it may contain path separators
+    // or `..`, so never use it as a path as is.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    // The part's `Content-Type`; absent means `text/plain`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>, MultipartError> {
+        let mut bytes = Vec::new();
+        self.copy_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // The content as text, with invalid UTF-8 replaced.
+    pub fn read_text(&mut self) -> Result<String, MultipartError> {
+        Ok(String::from_utf8_lossy(&self.read_bytes()?).into_owned())
+    }
+
+    // Streams the content into a new file at `path`, replacing any existing one. Returns
+    // the number of bytes written; on failure the file is removed.
+    pub fn save_to(&mut self, path: impl AsRef<Path>) -> Result<u64, MultipartError> {
+        let path = path.as_ref();
+        let mut file = File::create(path).map_err(MultipartError::Io)?;
+        let result = self.copy_to(&mut file);
+        if result.is_err() {
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+
+    // Streams the content into a new file in the temp directory, which is deleted when
+    // the returned `TempFile` is dropped unless it is persisted.
+    pub fn save_temp(&mut self) -> Result<TempFile, MultipartError> {
+        let (mut temp, mut file) = TempFile::create(&self.multipart.temp_dir).map_err(MultipartError::Io)?;
+        temp.size = self.copy_to(&mut file)?;
+        Ok(temp)
+    }
+
+    fn copy_to<W: Write>(&mut self, out: &mut W) -> Result<u64, MultipartError> {
+        let mut chunk = [0u8; READ_CHUNK];
+        let mut written = 0;
+        loop {
+            let n = self.multipart.read_data(&mut chunk)?;
+            if n == 0 {
+                return Ok(written);
+            }
+            out.write_all(&chunk[..n]).map_err(MultipartError::Io)?;
+            written += n as u64;
+        }
+    }
+}
+
+impl<R: Read> Read for Part<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.multipart.read_data(buf).map_err(|e| match e {
+            MultipartError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        })
+    }
+}
+
+// An uploaded file in the temp directory, removed on drop unless `persist`ed.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    size: u64,
+    keep: bool,
+}
+
+impl TempFile {
+    // Creates an empty file in `dir`, removed on drop.
+    pub(crate) fn create(dir: &Path) -> io::Result<(Self, File)> {
+        let (path, file) = create_temp_file(dir)?;
+        Ok((TempFile { path, size: 0, keep: false }, file))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // In bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // Moves the file to `path`, keeping it.
+    pub fn persist(mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if fs::rename(&self.path, path).is_err() {
+            // Renames fail across file systems.
+            fs::copy(&self.path, path)?;
+            let _ = fs::remove_file(&self.path);
+        }
+        self.keep = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn create_temp_file(dir: &Path) -> io::Result<(PathBuf, File)> {
+    loop {
+        let mut bytes = [0u8; 12];
+        getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+        let name: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let path = dir.join(format!("upload-{}.tmp", name));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// The boundary of a `multipart/form-data` Content-Type, e.g.
+// `multipart/form-data; boundary=----WebKitFormBoundary7MA4YWxkTrZu0gW`.
+pub(crate) fn boundary(content_type: &str) -> Result<String, MultipartError> {
+    let (media_type, params) = parse_params(content_type);
+    if !media_type.eq_ignore_ascii_case("multipart/form-data") {
+        return Err(MultipartError::UnsupportedMediaType(Some(media_type.to_ascii_lowercase())));
+    }
+    let boundary = params
+        .into_iter()
+        .find(|(name, _)| name == "boundary")
+        .map(|(_, value)| value)
+        .ok_or(MultipartError::Malformed("missing boundary"))?;
+    // RFC 2046: 1 to 70 characters.
+    if boundary.is_empty() || boundary.len() > 70 {
+        return Err(MultipartError::Malformed("invalid boundary"));
+    }
+    Ok(boundary)
+}
+
+// An RFC 8187 `filename*` value such as `UTF-8''na%C3%AFve.txt`.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    charset
+        .eq_ignore_ascii_case("utf-8")
+        .then(|| crate::path_pattern::percent_decode(encoded))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// Why a multipart body could not be read. Converts into a 415, 400, 413 or 500 response.
+#[derive(Debug)]
+pub enum MultipartError {
+    UnsupportedMediaType(Option<String>),
+    Malformed(&'static str),
+    TooManyParts(usize),
+    PartTooLarge(u64),
+    TooLarge(u64),
+    // Reading the input or writing a file failed.
+    Io(io::Error),
+}
+
+impl MultipartError {
+    pub fn status(&self) -> u16 {
+        match self {
+            MultipartError::UnsupportedMediaType(_) => 415,
+            MultipartError::Malformed(_) => 400,
+            MultipartError::TooManyParts(_) | MultipartError::PartTooLarge(_) | MultipartError::TooLarge(_) => 413,
+            MultipartError::Io(_) => 500,
+        }
+    }
+}
+
+impl fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultipartError::UnsupportedMediaType(Some(media_type)) => {
+                write!(f, "expected multipart/form-data, got {}", media_type)
+            }
+            MultipartError::UnsupportedMediaType(None) => write!(f, "expected multipart/form-data"),
+            MultipartError::Malformed(reason) => write!(f, "malformed multipart body: {}", reason),
+            MultipartError::TooManyParts(max) => write!(f, "more than {} parts", max),
+            MultipartError::PartTooLarge(max) => write!(f, "part larger than {} bytes", max),
+            MultipartError::TooLarge(max) => write!(f, "multipart body larger than {} bytes", max),
+            MultipartError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+impl From<MultipartError> for Response {
+    fn from(err: MultipartError) -> Self {
+        let response = Response::new(err.status());
+        // I/O details are for the server log, not the client.
+        let body = match err {
+            MultipartError::Io(_) => format!("{} {}", response.status_code, response.reason_phrase),
+            err => format!("{} {}: {}", response.status_code, response.reason_phrase, err),
+        };
+        response.with_body(&body)
+    }
+}
@@ -0,0 +1,129 @@
+// Parsing of the `Accept*` request headers and server-driven content negotiation.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityItem {
+    pub value: String,
+    pub quality: f32,
+}
+
+// Parses a header such as `text/html, application/json;q=0.9, */*;q=0.1` into its items,
+// sorted by descending quality. Items keep their header order when qualities are equal.
+// Media type parameters other than `q` are dropped.
+pub fn parse_quality_list(header: &str) -> Vec<QualityItem> {
+    let mut items: Vec<QualityItem> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let value = parts.next().filter(|v| !v.is_empty())?.to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                .map(|(_, q)| q.trim().parse::<f32>().unwrap_or(0.0).clamp(0.0, 1.0))
+                .unwrap_or(1.0);
+            Some(QualityItem { value, quality })
+        })
+        .collect();
+    items.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+    items
+}
+
+// Picks the best of `available` media types for an `Accept` header. Without a header
+// the first available type wins; `None` means nothing is acceptable (406).
+pub fn negotiate_media_type<'a>(accept: Option<&str>, available: &[&'a str]) -> Option<&'a str> {
+    let Some(accept) = accept else {
+        return available.first().copied();
+    };
+    let ranges = parse_quality_list(accept);
+    best_match(available, |candidate| {
+        let (kind, subtype) = split_media_type(candidate);
+        ranges
+            .iter()
+            .filter_map(|range| {
+                let (range_kind, range_subtype) = split_media_type(&range.value);
+                let specificity = match (range_kind, range_subtype) {
+                    ("*", "*") => 0,
+                    (k, "*") if k == kind => 1,
+                    (k, s) if k == kind && s == subtype => 2,
+                    _ => return None,
+                };
+                Some((specificity, range.quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality)
+    })
+}
+
+// Picks the best of `available` language tags for an `Accept-Language` header, using
+// prefix matching so that a range of `en` accepts `en-US`.
+pub fn negotiate_language<'a>(accept_language: Option<&str>, available: &[&'a str]) -> Option<&'a str> {
+    let Some(header) = accept_language else {
+        return available.first().copied();
+    };
+    let ranges = parse_quality_list(header);
+    best_match(available, |candidate| {
+        ranges
+            .iter()
+            .filter(|range| {
+                range.value == "*"
+                    || candidate == range.value
+                    || candidate
+                        .strip_prefix(range.value.as_str())
+                        .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .max_by_key(|range| range.value.len())
+            .map(|range| range.quality)
+    })
+}
+
+// Picks the best of `available` content codings for an `Accept-Encoding` header.
+// `identity` is acceptable unless explicitly refused, as RFC 9110 requires.
+pub fn negotiate_encoding<'a>(accept_encoding: Option<&str>, available: &[&'a str]) -> Option<&'a str> {
+    let Some(header) = accept_encoding else {
+        return available.iter().copied().find(|e| e.eq_ignore_ascii_case("identity"));
+    };
+    let codings = parse_quality_list(header);
+    best_match(available, |candidate| {
+        codings
+            .iter()
+            .find(|coding| coding.value == candidate)
+            .or_else(|| codings.iter().find(|coding| coding.value == "*"))
+            .map(|coding| coding.quality)
+            .or(if candidate == "identity" { Some(0.001) } else { None })
+    })
+}
+
+// Picks the best of `available` charsets for an `Accept-Charset` header.
+pub fn negotiate_charset<'a>(accept_charset: Option<&str>, available: &[&'a str]) -> Option<&'a str> {
+    let Some(header) = accept_charset else {
+        return available.first().copied();
+    };
+    let charsets = parse_quality_list(header);
+    best_match(available, |candidate| {
+        charsets
+            .iter()
+            .find(|charset| charset.value == candidate)
+            .or_else(|| charsets.iter().find(|charset| charset.value == "*"))
+            .map(|charset| charset.quality)
+    })
+}
+
+// Returns the available value with the highest positive quality; ties go to the
+// earlier entry in `available`, i.e. the server's preference.
+fn best_match<'a, F>(available: &[&'a str], quality_of: F) -> Option<&'a str>
+where
+    F: Fn(&str) -> Option<f32>,
+{
+    let mut best: Option<(&'a str, f32)> = None;
+    for candidate in available {
+        let quality = quality_of(&candidate.to_ascii_lowercase()).unwrap_or(0.0);
+        if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+            best = Some((candidate, quality));
+        }
+    }
+    best.map(|(candidate, _)| candidate)
+}
+
+fn split_media_type(media_type: &str) -> (&str, &str) {
+    let essence = media_type.split(';').next().unwrap_or("").trim();
+    essence.split_once('/').unwrap_or((essence, ""))
+}
@@ -0,0 +1,204 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+// A route path pattern. Segments are either literals or parameters:
+// - `{id}`             any non-empty segment
+// - `{id:int}`         an optionally signed integer
+// - `{id:uuid}`        a UUID in its hyphenated form
+// - `{name:slug}`      lowercase letters, digits and single dashes
+// - `{code:[A-Z]{3}}`  anything else is treated as a regex the whole segment must match
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Param { name: String, constraint: Constraint },
+}
+
+#[derive(Debug, Clone)]
+pub enum Constraint {
+    Any,
+    Int,
+    Uuid,
+    Slug,
+    Regex(Regex),
+}
+
+impl Constraint {
+    fn parse(spec: &str, pattern: &str) -> Self {
+        match spec {
+            "int" => Constraint::Int,
+            "uuid" => Constraint::Uuid,
+            "slug" => Constraint::Slug,
+            regex => match Regex::new(&format!("^(?:{})$", regex)) {
+                Ok(regex) => Constraint::Regex(regex),
+                Err(e) => panic!("Invalid route pattern '{}': {}", pattern, e),
+            },
+        }
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        match self {
+            Constraint::Any => !value.is_empty(),
+            Constraint::Int => {
+                let digits = value.strip_prefix('-').unwrap_or(value);
+                !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+            }
+            Constraint::Uuid => is_uuid(value),
+            Constraint::Slug => {
+                !value.is_empty()
+                    && value.split('-').all(|part| {
+                        !part.is_empty()
+                            && part.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+                    })
+            }
+            Constraint::Regex(regex) => regex.is_match(value),
+        }
+    }
+
+    // Stable textual form used to detect conflicting registrations.
+    fn key(&self) -> String {
+        match self {
+            Constraint::Any => String::new(),
+            Constraint::Int => ":int".to_string(),
+            Constraint::Uuid => ":uuid".to_string(),
+            Constraint::Slug => ":slug".to_string(),
+            Constraint::Regex(regex) => format!(":{}", regex.as_str()),
+        }
+    }
+}
+
+impl PathPattern {
+    pub fn parse(pattern: &str) -> Self {
+        let segments = split_segments(pattern)
+            .into_iter()
+            .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(param) => {
+                    let (name, constraint) = match param.split_once(':') {
+                        Some((name, spec)) => (name, Constraint::parse(spec, pattern)),
+                        None => (param, Constraint::Any),
+                    };
+                    if name.is_empty() {
+                        panic!("Invalid route pattern '{}': parameter without a name", pattern);
+                    }
+                    Segment::Param {
+                        name: name.to_string(),
+                        constraint,
+                    }
+                }
+                None => Segment::Literal(segment.to_string()),
+            })
+            .collect();
+
+        Self {
+            pattern: pattern.to_string(),
+            segments,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.segments.iter().all(|s| matches!(s, Segment::Literal(_)))
+    }
+
+    // Two patterns with the same key match exactly the same paths.
+    pub fn key(&self) -> String {
+        let mut key = String::new();
+        for segment in &self.segments {
+            key.push('/');
+            match segment {
+                Segment::Literal(literal) => key.push_str(literal),
+                Segment::Param { constraint, .. } => {
+                    key.push('{');
+                    key.push_str(&constraint.key());
+                    key.push('}');
+                }
+            }
+        }
+        key
+    }
+
+    // Returns the captured, percent-decoded parameters if `path` matches.
+    pub fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let path = path.split('?').next().unwrap_or("");
+        let parts = split_segments(path);
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment {
+                Segment::Literal(literal) if literal == part => {}
+                Segment::Param { name, constraint } => {
+                    let value = percent_decode(part);
+                    if !constraint.is_match(&value) {
+                        return None;
+                    }
+                    params.insert(name.clone(), value);
+                }
+                _ => return None,
+            }
+        }
+        Some(params)
+    }
+}
+
+// Splits on `/`, ignoring slashes nested inside `{...}` so regex constraints can contain them.
+fn split_segments(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in path.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '/' if depth == 0 => {
+                if i > start {
+                    segments.push(&path[start..i]);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < path.len() {
+        segments.push(&path[start..]);
+    }
+    segments
+}
+
+pub fn is_uuid(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 36
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => *b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
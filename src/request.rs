@@ -1,17 +1,99 @@
+#[cfg(feature = "secure-cookies")]
+use crate::cookie::CookieKey;
+use crate::extensions::Extensions;
+use crate::form::{self, FormData, FormError};
+use crate::header::{HeaderMap, InvalidHeader};
+#[cfg(feature = "serde")]
+use crate::json::{self, JsonError};
+use crate::logger::{Logger, RequestLogger};
+use crate::multipart::{self, Multipart, MultipartError, TempFile};
 use crate::http_method::{HttpMethod, ParseHttpMethodError};
+use crate::negotiation::{self, QualityItem};
+use crate::path_pattern;
+use crate::Response;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::SocketAddr;
 use std::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
 
 #[derive(Debug, Clone)]
 pub struct Request {
     pub method: HttpMethod,
     pub path: String,
     pub version: String,
-    pub headers: Vec<(String, String)>,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    // Values captured by the router from host patterns like `{tenant}.example.com`
+    // and path patterns like `/users/{id:int}`.
+    pub params: HashMap<String, String>,
+    // Connection details filled in by the server; `None` for requests built by hand.
+    pub remote_addr: Option<SocketAddr>,
+    pub local_addr: Option<SocketAddr>,
+    pub tls: Option<TlsInfo>,
+    // Values attached by middlewares, e.g. the authenticated `Principal`.
+    pub extensions: Extensions,
+}
+
+// The ID of the current request, assigned by `RequestIds` (as a middleware or through
+// `Server::with_request_ids`). Available on the request (`req.request_id()`) and on the
+// response, where the server's `Logger` picks it up for the access and error logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub(crate) String);
+
+impl RequestId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// A request body spooled to disk by the server; the file is removed with the last copy
+// of the request.
+pub(crate) struct SpooledBody(pub(crate) TempFile);
+
+// See `Request::body_reader`.
+pub enum BodyReader<'a> {
+    Memory(&'a [u8]),
+    File(File),
+}
+
+impl Read for BodyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            BodyReader::Memory(bytes) => bytes.read(buf),
+            BodyReader::File(file) => file.read(buf),
+        }
+    }
+}
+
+// What was negotiated during the TLS handshake of the request's connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    pub version: String,
+    pub cipher_suite: String,
+    pub alpn_protocol: Option<String>,
+    pub server_name: Option<String>,
 }
 
 impl Request {
-    pub fn from_buffer(buffer: &[u8]) -> Result<Self, ParseHttpMethodError> {
-        let request_str = String::from_utf8_lossy(buffer);
+    // Parses a raw request. Bytes after the blank line are taken as the body, up to
+    // `Content-Length` (the server reads the body itself and only passes the head).
+    pub fn from_buffer(buffer: &[u8]) -> Result<Self, ParseRequestError> {
+        let (head, rest) = match find_head_end(buffer) {
+            Some(end) => (&buffer[..end], &buffer[end..]),
+            None => (buffer, &[][..]),
+        };
+        let request_str = String::from_utf8_lossy(head);
         let mut lines = request_str.lines();
 
         // Parse request line
@@ -22,43 +104,50 @@ impl Request {
         let path = parts.next().unwrap_or("/").to_string();
         let version = parts.next().unwrap_or("HTTP/1.1").to_string();
 
-        // Parse headers
-        let mut headers = Vec::new();
+        // Parse headers. A line with an invalid name or value fails the whole request;
+        // whitespace around the name (`Name : value`, or a folded line) counts as part of
+        // it, which RFC 9112 requires to be rejected rather than guessed at.
+        let mut headers = HeaderMap::new();
         for line in lines {
             if line.trim().is_empty() {
                 break; // End of headers
             }
 
-            if let Some((key, value)) = line.split_once(":") {
-                headers.push((key.trim().to_string(), value.trim().to_string()));
-            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| InvalidHeader::Name(line.to_string()))?;
+            headers.append(key, value.trim())?;
         }
 
+        let body_len = headers.content_length().unwrap_or(0).min(rest.len() as u64) as usize;
+        let body = rest[..body_len].to_vec();
+
         Ok(Self {
             method,
             path,
             version,
             headers,
+            body,
+            params: HashMap::new(),
+            remote_addr: None,
+            local_addr: None,
+            tls: None,
+            extensions: Extensions::new(),
         })
     }
 
-    pub fn to_string(&self) -> String {
-        let mut request = format!("{} {} {}\r\n", self.method, self.path, self.version);
-
-        for (key, value) in &self.headers {
-            request.push_str(&format!("{}: {}\r\n", key, value));
-        }
-
-        request.push_str("\r\n");
-        request
-    }
-
     pub fn get(path: &str) -> Self {
         Self {
             method: HttpMethod::GET,
             path: String::from(path),
             version: String::from("HTTP/1.1"),
-            headers: vec![],
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            params: HashMap::new(),
+            remote_addr: None,
+            local_addr: None,
+            tls: None,
+            extensions: Extensions::new(),
         }
     }
 
@@ -67,12 +156,325 @@ impl Request {
             method: HttpMethod::POST,
             path: String::from(path),
             version: String::from("HTTP/1.1"),
-            headers: vec![],
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            params: HashMap::new(),
+            remote_addr: None,
+            local_addr: None,
+            tls: None,
+            extensions: Extensions::new(),
         }
     }
 
+    // Panics on header names or values that could not be sent, e.g. ones containing CR/LF.
     pub fn with_header(mut self, key: &str, value: &str) -> Self {
-        self.headers.push((key.to_string(), value.to_string()));
+        if let Err(e) = self.headers.append(key, value) {
+            panic!("Request::with_header: {}", e);
+        }
+        self
+    }
+
+    // Sets the body along with a matching `Content-Length`.
+    pub fn with_body(self, body: &str) -> Self {
+        self.with_bytes(body.as_bytes().to_vec())
+    }
+
+    pub fn with_bytes(mut self, body: Vec<u8>) -> Self {
+        let _ = self.headers.insert("Content-Length", &body.len().to_string());
+        self.body = body;
+        self
+    }
+
+    // The body as text, with invalid UTF-8 replaced.
+    pub fn body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    // The decoded query string; empty without one.
+    pub fn query(&self) -> FormData {
+        let query = self.path.split_once('?').map(|(_, query)| query).unwrap_or("");
+        FormData::parse(query)
+    }
+
+    // Decodes an `application/x-www-form-urlencoded` body, allowing up to
+    // `form::DEFAULT_MAX_FIELDS` fields. The error converts into a 415 or 413 response.
+    pub fn form(&self) -> Result<FormData, FormError> {
+        let mime_type = self.headers.mime_type();
+        if mime_type.as_deref() != Some("application/x-www-form-urlencoded") {
+            return Err(FormError::UnsupportedMediaType(mime_type));
+        }
+        FormData::parse_limited(&self.body_text(), form::DEFAULT_MAX_FIELDS)
+    }
+
+    // Deserializes the form body, e.g. into a `#[derive(Deserialize)]` struct. Values that
+    // don't fit give a 422.
+    #[cfg(feature = "serde")]
+    pub fn form_as<T: DeserializeOwned>(&self) -> Result<T, FormError> {
+        self.form()?.deserialize()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn query_as<T: DeserializeOwned>(&self) -> Result<T, FormError> {
+        self.query().deserialize()
+    }
+
+    // A parser over a `multipart/form-data` body, reading it through `body_reader`. The
+    // error converts into a 415 or 400 response.
+    pub fn multipart(&self) -> Result<Multipart<BodyReader<'_>>, MultipartError> {
+        let content_type = self
+            .headers
+            .content_type()
+            .ok_or(MultipartError::UnsupportedMediaType(None))?;
+        let boundary = multipart::boundary(content_type)?;
+        let reader = self.body_reader().map_err(MultipartError::Io)?;
+        Ok(Multipart::new(reader, &boundary))
+    }
+
+    // The body as a reader. Multipart uploads larger than `Limits::spool_multipart_over`
+    // are written to a temp file as they arrive instead of into `body`, which stays
+    // empty; this (and `multipart`) reads them from there. Each call starts over.
+    pub fn body_reader(&self) -> io::Result<BodyReader<'_>> {
+        match self.extension::<SpooledBody>() {
+            Some(SpooledBody(file)) => Ok(BodyReader::File(File::open(file.path())?)),
+            None => Ok(BodyReader::Memory(&self.body)),
+        }
+    }
+
+    // Whether the body was spooled to a temp file rather than read into `body`.
+    pub fn is_body_spooled(&self) -> bool {
+        self.extensions.contains::<SpooledBody>()
+    }
+
+    pub fn with_remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.remote_addr = Some(addr);
         self
     }
-}
\ No newline at end of file
+
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get::<T>()
+    }
+
+    // The ID assigned by `RequestIds`.
+    pub fn request_id(&self) -> Option<&str> {
+        self.extension::<RequestId>().map(RequestId::as_str)
+    }
+
+    // Writes to the server's error log from handlers and middlewares, tagged with this
+    // request's ID: `req.logger().error("payment provider timed out")`.
+    pub fn logger(&self) -> RequestLogger {
+        RequestLogger::new(self.extension::<Logger>().cloned(), self.extension::<RequestId>().cloned())
+    }
+
+    // "https" when the connection was accepted through TLS, "http" otherwise.
+    pub fn scheme(&self) -> &'static str {
+        if self.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    // Returns the first header with the given name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    // The value of the first cookie named `name` in the `Cookie` header(s).
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.headers
+            .cookies()
+            .into_iter()
+            .find(|(cookie, _)| *cookie == name)
+            .map(|(_, value)| value)
+    }
+
+    // A cookie issued with `CookieKey::sign`, if its signature is valid.
+    #[cfg(feature = "secure-cookies")]
+    pub fn signed_cookie(&self, name: &str, key: &CookieKey) -> Option<String> {
+        key.verify(name, self.cookie(name)?)
+    }
+
+    // A cookie issued with `CookieKey::encrypt`, decrypted.
+    #[cfg(feature = "secure-cookies")]
+    pub fn private_cookie(&self, name: &str, key: &CookieKey) -> Option<String> {
+        key.decrypt(name, self.cookie(name)?)
+    }
+
+    // Deserializes a JSON body. Requires a JSON `Content-Type`; the error converts into
+    // a 415, 400 or 422 response.
+    #[cfg(feature = "serde")]
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, JsonError> {
+        let mime_type = self.headers.mime_type();
+        if !mime_type.as_deref().is_some_and(json::is_json) {
+            return Err(JsonError::UnsupportedMediaType(mime_type));
+        }
+        serde_json::from_slice(&self.body).map_err(JsonError::from_serde)
+    }
+
+    // Returns the requested host name without the port.
+    // HTTP/1.1 only has `Host`; an HTTP/2 `:authority` would be consulted here as well.
+    pub fn host(&self) -> Option<&str> {
+        self.header("Host")
+            .map(crate::host::strip_port)
+            .filter(|host| !host.is_empty())
+    }
+
+    pub fn accept(&self) -> Vec<QualityItem> {
+        self.quality_list("Accept")
+    }
+
+    pub fn accept_language(&self) -> Vec<QualityItem> {
+        self.quality_list("Accept-Language")
+    }
+
+    pub fn accept_encoding(&self) -> Vec<QualityItem> {
+        self.quality_list("Accept-Encoding")
+    }
+
+    pub fn accept_charset(&self) -> Vec<QualityItem> {
+        self.quality_list("Accept-Charset")
+    }
+
+    fn quality_list(&self, header: &str) -> Vec<QualityItem> {
+        self.header(header)
+            .map(negotiation::parse_quality_list)
+            .unwrap_or_default()
+    }
+
+    // Picks the best media type the client accepts, e.g.
+    // `req.negotiate(&["application/json", "text/html"])`. `None` means 406.
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiation::negotiate_media_type(self.header("Accept"), available)
+    }
+
+    pub fn negotiate_language<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiation::negotiate_language(self.header("Accept-Language"), available)
+    }
+
+    pub fn negotiate_encoding<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiation::negotiate_encoding(self.header("Accept-Encoding"), available)
+    }
+
+    pub fn negotiate_charset<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        negotiation::negotiate_charset(self.header("Accept-Charset"), available)
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|v| v.as_str())
+    }
+
+    // Parses a captured parameter; the error converts into a 400 response via `Response::from`.
+    pub fn param_as<T: FromStr>(&self, name: &str) -> Result<T, ParamError> {
+        let value = self.param(name).ok_or_else(|| ParamError::missing(name))?;
+        value.parse().map_err(|_| ParamError::invalid(name, value))
+    }
+
+    pub fn param_u64(&self, name: &str) -> Result<u64, ParamError> {
+        self.param_as(name)
+    }
+
+    pub fn param_i64(&self, name: &str) -> Result<i64, ParamError> {
+        self.param_as(name)
+    }
+
+    // Returns the parameter if it is a hyphenated UUID, lowercased.
+    pub fn param_uuid(&self, name: &str) -> Result<String, ParamError> {
+        let value = self.param(name).ok_or_else(|| ParamError::missing(name))?;
+        if path_pattern::is_uuid(value) {
+            Ok(value.to_ascii_lowercase())
+        } else {
+            Err(ParamError::invalid(name, value))
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}\r\n", self.method, self.path, self.version)?;
+
+        for (key, value) in self.headers.iter() {
+            write!(f, "{}: {}\r\n", key, value)?;
+        }
+
+        write!(f, "\r\n{}", self.body_text())
+    }
+}
+
+// Index just past the `\r\n\r\n` (or bare `\n\n`) that ends the request head.
+pub(crate) fn find_head_end(buffer: &[u8]) -> Option<usize> {
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| i + 2);
+    match (crlf, lf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamError {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl ParamError {
+    fn missing(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: None,
+        }
+    }
+
+    fn invalid(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "invalid value '{}' for parameter '{}'", value, self.name),
+            None => write!(f, "missing parameter '{}'", self.name),
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+// Why `Request::from_buffer` couldn't parse a request head.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRequestError {
+    Method(ParseHttpMethodError),
+    Header(InvalidHeader),
+}
+
+impl fmt::Display for ParseRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRequestError::Method(_) => write!(f, "invalid request method"),
+            ParseRequestError::Header(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseRequestError {}
+
+impl From<ParseHttpMethodError> for ParseRequestError {
+    fn from(e: ParseHttpMethodError) -> Self {
+        ParseRequestError::Method(e)
+    }
+}
+
+impl From<InvalidHeader> for ParseRequestError {
+    fn from(e: InvalidHeader) -> Self {
+        ParseRequestError::Header(e)
+    }
+}
+
+impl From<ParamError> for Response {
+    fn from(err: ParamError) -> Self {
+        Response::new(400).with_body(&format!("400 Bad Request: {}", err))
+    }
+}
@@ -1,12 +1,22 @@
+use std::borrow::Cow;
 use std::fs;
 use chrono::Utc;
 use mime_guess::from_path;
 
+use crate::cookie::Cookie;
+use crate::extensions::Extensions;
+use crate::header::{HeaderMap, InvalidHeader};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
 pub struct Response {
     pub status_code: u16,
     pub reason_phrase: String,
-    pub headers: Vec<(String, String)>,
-    pub body: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    // Values for the server and outer middlewares, e.g. the `RequestId` for logging.
+    // Never serialized.
+    pub extensions: Extensions,
 }
 
 impl Response {
@@ -14,45 +24,107 @@ impl Response {
         Self {
             status_code,
             reason_phrase: Self::get_reason_phrase(status_code).to_string(),
-            headers: Vec::new(),
-            body: String::new(),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            extensions: Extensions::new(),
         }
     }
 
     pub fn with_body(mut self, body: &str) -> Self {
-        self.body = body.to_string();
+        self.body = body.as_bytes().to_vec();
+        self
+    }
+
+    pub fn with_bytes(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
         self
     }
 
+    // The body as text, with invalid UTF-8 replaced.
+    pub fn body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    // Appends a header. Panics on names or values that would corrupt the response,
+    // such as values containing CR/LF; use `try_with_header` for untrusted input.
     pub fn with_header(mut self, key: &str, value: &str) -> Self {
-        self.headers.push((key.to_string(), value.to_string()));
+        self.append_header(key, value);
         self
     }
 
+    pub fn try_with_header(mut self, key: &str, value: &str) -> Result<Self, InvalidHeader> {
+        self.headers.append(key, value)?;
+        Ok(self)
+    }
+
+    // Replaces any existing values of the header. Panics like `with_header`.
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        if let Err(e) = self.headers.insert(key, value) {
+            panic!("Response header rejected: {}", e);
+        }
+    }
+
+    pub fn append_header(&mut self, key: &str, value: &str) {
+        if let Err(e) = self.headers.append(key, value) {
+            panic!("Response header rejected: {}", e);
+        }
+    }
+
+    // Adds a `Set-Cookie` header. Panics like `with_header` if the cookie is not valid.
+    pub fn with_cookie(self, cookie: Cookie) -> Self {
+        match self.try_with_cookie(cookie) {
+            Ok(response) => response,
+            Err(e) => panic!("Response header rejected: {}", e),
+        }
+    }
+
+    pub fn try_with_cookie(self, cookie: Cookie) -> Result<Self, InvalidHeader> {
+        if !cookie.is_valid() {
+            return Err(InvalidHeader::Value("Set-Cookie".to_string()));
+        }
+        self.try_with_header("Set-Cookie", &cookie.to_string())
+    }
+
     pub fn from_file(path: &str) -> Self {
-        match fs::read_to_string(path) {
+        match fs::read(path) {
             Ok(contents) => {
                 let mime = from_path(path).first_or_octet_stream();
                 Self::new(200)
-                    .with_body(&contents)
+                    .with_bytes(contents)
                     .with_header("Content-Type", mime.essence_str())
             }
             Err(_) => Self::new(404).with_body("404 Not Found"),
         }
     }
 
+    // A 200 response with `value` as its JSON body.
+    #[cfg(feature = "serde")]
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Self {
+        Self::new(200).with_json(value)
+    }
+
+    // Sets `value` as the JSON body. A value that can't be serialized (such as a map with
+    // non-string keys) turns the response into a 500.
+    #[cfg(feature = "serde")]
+    pub fn with_json<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => {
+                self.body = body;
+                self.set_header("Content-Type", "application/json");
+                self
+            }
+            Err(_) => Self::new(500).with_body("500 Internal Server Error"),
+        }
+    }
+
     pub fn parse_headers(mut self, headers_str: &str) -> Self {
-        self.headers = headers_str
-            .lines()
-            .filter(|line| !line.is_empty())
-            .map(|line| {
-                let mut parts = line.splitn(2, ": ");
-                (
-                    parts.next().unwrap_or_default().to_string(),
-                    parts.next().unwrap_or_default().to_string(),
-                )
-            })
-            .collect();
+        self.headers = HeaderMap::new();
+        for line in headers_str.lines().filter(|line| !line.is_empty()) {
+            let mut parts = line.splitn(2, ": ");
+            let key = parts.next().unwrap_or_default();
+            let value = parts.next().unwrap_or_default();
+            let _ = self.headers.append(key, value);
+        }
         self
     }
 
@@ -60,22 +132,23 @@ impl Response {
         let headers = self.prepare_headers();
         let mut response = format!("HTTP/1.1 {} {}\r\n", self.status_code, self.reason_phrase);
 
-        for (key, value) in headers {
+        for (key, value) in headers.iter() {
             response.push_str(&format!("{}: {}\r\n", key, value));
         }
 
         response.push_str("\r\n");
-        response.push_str(&self.body);
 
-        response.into_bytes()
+        let mut response = response.into_bytes();
+        response.extend_from_slice(&self.body);
+        response
     }
 
-    fn prepare_headers(&self) -> Vec<(String, String)> {
+    fn prepare_headers(&self) -> HeaderMap {
         let mut final_headers = self.headers.clone();
 
         let mut insert_if_missing = |name: &str, value: String| {
-            if !final_headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(name)) {
-                final_headers.push((name.to_string(), value));
+            if !final_headers.contains(name) {
+                let _ = final_headers.append(name, &value);
             }
         };
 
@@ -90,6 +163,7 @@ impl Response {
 
     fn get_reason_phrase(status_code: u16) -> &'static str {
         match status_code {
+            100 => "Continue",
             200 => "OK",
             201 => "Created",
             204 => "No Content",
@@ -100,9 +174,19 @@ impl Response {
             403 => "Forbidden",
             404 => "Not Found",
             405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            408 => "Request Timeout",
+            411 => "Length Required",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            417 => "Expectation Failed",
+            422 => "Unprocessable Entity",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
             500 => "Internal Server Error",
             501 => "Not Implemented",
             503 => "Service Unavailable",
+            504 => "Gateway Timeout",
             _ => "Unknown",
         }
     }
@@ -1,31 +1,92 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crate::extensions::Extensions;
+use crate::host::HostPattern;
 use crate::http_method::HttpMethod;
 use crate::middleware::{self, Middleware};
+use crate::path_pattern::PathPattern;
 use crate::{Handler, Request, Response};
 
 #[derive(Clone)]
 struct RouteDefinition {
     handler: Arc<dyn Handler>,
     middlewares: Vec<Arc<dyn Middleware>>,
+    name: Option<String>,
+    produces: Option<String>,
+    timeout: Option<Duration>,
+    max_body_size: Option<usize>,
 }
 
+#[derive(Clone)]
+struct RouteEntry {
+    pattern: PathPattern,
+    // Several definitions per method only when they produce different media types.
+    methods: HashMap<HttpMethod, Vec<RouteDefinition>>,
+}
+
+enum RouteMatch<'a> {
+    Found(&'a RouteDefinition, HashMap<String, String>),
+    HeadFallback(&'a RouteDefinition, HashMap<String, String>),
+    MethodNotAllowed,
+    NotAcceptable,
+    NotFound,
+}
+
+// Read-only view of a registered route, as returned by `Router::routes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo<'a> {
+    pub host: Option<&'a str>,
+    pub method: &'a HttpMethod,
+    pub pattern: &'a str,
+    pub produces: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub middleware_count: usize,
+}
+
+// Cloning a router is cheap: the route table is shared until the clone is modified.
 #[derive(Clone)]
 pub struct Router {
-    routes: HashMap<String, HashMap<HttpMethod, RouteDefinition>>,
     global_middlewares: Vec<Arc<dyn Middleware>>,
+    table: Arc<RouteTable>,
+    state: Extensions,
+}
+
+#[derive(Clone, Default)]
+struct RouteTable {
+    routes: Vec<RouteEntry>,
+    last_route: Option<(usize, HttpMethod, usize)>,
+    route_listing_path: Option<String>,
+    hosts: Vec<(HostPattern, Router)>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Router {
     pub fn new() -> Self {
         Self {
-            routes: HashMap::new(),
             global_middlewares: Vec::new(),
+            table: Arc::new(RouteTable::default()),
+            state: Extensions::new(),
         }
     }
 
-    // Adds a global middleware that will be applied to all routes.
+    // Shares `value` with all handlers and middlewares through the request extensions,
+    // where the `State` extractor finds it. Holds one value per type; state of a host
+    // router replaces state of the same type from the outer router.
+    pub fn state<T: Send + Sync + 'static>(&mut self, value: T) -> &mut Self {
+        self.state.insert(value);
+        self
+    }
+
+    // Adds a global middleware that will be applied to all requests, including ones that
+    // end in 404/405/406, so it can answer requests (e.g. CORS preflights) itself.
     pub fn use_global<M>(&mut self, middleware: M) -> &mut Self
     where
         M: Middleware + 'static,
@@ -34,30 +95,209 @@ impl Router {
         self
     }
 
+    // Sends requests whose `Host` matches `pattern` to `router` instead of the routes
+    // registered here, which act as the default. When several patterns match, the most
+    // specific wins regardless of registration order, so `{tenant}.tenants.example.com`
+    // is not shadowed by `*.example.com`; captures like `{tenant}.example.com` end up in `req.params`.
+    // Global middlewares of this router run before the host router's own chain.
+    pub fn host(&mut self, pattern: &str, router: Router) -> &mut Self {
+        let pattern = HostPattern::parse(pattern);
+        let table = Arc::make_mut(&mut self.table);
+        if table.hosts.iter().any(|(existing, _)| *existing == pattern) {
+            panic!("Route conflict: host '{}' is already registered", pattern.as_str());
+        }
+        if let Some(listing_path) = &table.route_listing_path {
+            if router.table.has_static_route(listing_path) {
+                panic!("Route conflict: {} is the route listing and can't be routed on host '{}'", listing_path, pattern.as_str());
+            }
+        }
+        table.hosts.push((pattern, router));
+        self
+    }
+
     fn add_route_internal<H>(
         &mut self,
         method: HttpMethod,
         path: &str,
+        produces: Option<&str>,
         handler: H,
         route_middlewares: Vec<Arc<dyn Middleware>>,
     ) where
         H: Handler + 'static,
     {
-        let route_def = RouteDefinition {
+        let table = Arc::make_mut(&mut self.table);
+        let pattern = PathPattern::parse(path);
+        if let Some(listing_path) = &table.route_listing_path {
+            if pattern.is_static() && pattern.matches(listing_path).is_some() {
+                panic!("Route conflict: {} {} is the route listing", method, path);
+            }
+        }
+        let key = pattern.key();
+        let index = match table.routes.iter().position(|entry| entry.pattern.key() == key) {
+            Some(index) => index,
+            None => {
+                table.routes.push(RouteEntry {
+                    pattern,
+                    methods: HashMap::new(),
+                });
+                table.routes.len() - 1
+            }
+        };
+
+        let entry = &mut table.routes[index];
+        let produces = produces.map(|media_type| media_type.trim().to_ascii_lowercase());
+        let definitions = entry.methods.entry(method.clone()).or_default();
+        if definitions.iter().any(|def| def.produces == produces) {
+            let media_type = produces.map(|m| format!(" producing {}", m)).unwrap_or_default();
+            if entry.pattern.as_str() == path {
+                panic!("Route conflict: {} {}{} is already registered", method, path, media_type);
+            }
+            panic!(
+                "Route conflict: {} {}{} is already registered as {}",
+                method,
+                path,
+                media_type,
+                entry.pattern.as_str()
+            );
+        }
+
+        definitions.push(RouteDefinition {
             handler: Arc::new(handler),
             middlewares: route_middlewares,
+            name: None,
+            produces,
+            timeout: None,
+            max_body_size: None,
+        });
+        table.last_route = Some((index, method, definitions.len() - 1));
+    }
+
+    // Registers a handler that is only chosen when the client accepts `media_type`.
+    // Several media types can share a method and path; when none of them is acceptable
+    // the plain route for that method (if any) is used, otherwise the router answers 406.
+    pub fn route_producing<H>(
+        &mut self,
+        method: HttpMethod,
+        path: &str,
+        media_type: &str,
+        handler: H,
+    ) -> &mut Self
+    where
+        H: Handler + 'static,
+    {
+        self.add_route_internal(method, path, Some(media_type), handler, vec![]);
+        self
+    }
+
+    pub fn get_producing<H>(&mut self, path: &str, media_type: &str, handler: H) -> &mut Self
+    where
+        H: Handler + 'static,
+    {
+        self.route_producing(HttpMethod::GET, path, media_type, handler)
+    }
+
+    // Names the most recently registered route, e.g. `router.get("/", h).name("home")`.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        if self.routes().any(|route| route.name == Some(name)) {
+            panic!("Route conflict: a route named '{}' is already registered", name);
+        }
+
+        let table = Arc::make_mut(&mut self.table);
+        let (index, method, variant) = table
+            .last_route
+            .clone()
+            .expect("Router::name called before any route was registered");
+        if let Some(route_def) = table.routes[index]
+            .methods
+            .get_mut(&method)
+            .and_then(|definitions| definitions.get_mut(variant))
+        {
+            route_def.name = Some(name.to_string());
+        }
+        self
+    }
+
+    // Overrides the server's handler timeout for the most recently registered route,
+    // e.g. `router.post("/reports", h).timeout(Duration::from_secs(120))`.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        if let Some(route_def) = self.last_route_mut("Router::timeout") {
+            route_def.timeout = Some(timeout);
+        }
+        self
+    }
+
+    // The handler timeout set with `timeout` for the route `req` would be dispatched to.
+    pub fn route_timeout(&self, req: &Request) -> Option<Duration> {
+        self.table.route_definition(req).and_then(|route_def| route_def.timeout)
+    }
+
+    // Overrides the server's body size limit for the most recently registered route,
+    // e.g. `router.post("/uploads", h).max_body_size(100 * 1024 * 1024)`.
+    pub fn max_body_size(&mut self, limit: usize) -> &mut Self {
+        if let Some(route_def) = self.last_route_mut("Router::max_body_size") {
+            route_def.max_body_size = Some(limit);
+        }
+        self
+    }
+
+    // The body size limit set with `max_body_size` for the route `req` would be
+    // dispatched to. Only the request head is needed.
+    pub fn route_max_body_size(&self, req: &Request) -> Option<usize> {
+        self.table.route_definition(req).and_then(|route_def| route_def.max_body_size)
+    }
+
+    // Asks the middlewares `req` would pass through whether to accept its body, for
+    // `Expect: 100-continue`. `None` means go ahead. A rejection travels back out through
+    // the middlewares ahead of the one that made it, as a response from `handle` would,
+    // so it gets their headers (CORS, security headers, request ID).
+    pub fn on_expect_continue(&self, req: &Request) -> Option<Response> {
+        let mut req = req.clone();
+        req.extensions.extend(&self.state);
+        let (depth, response) = match first_rejection(&self.global_middlewares, &req) {
+            Some(rejection) => rejection,
+            None => (self.global_middlewares.len(), self.table.on_expect_continue(&req)?),
         };
-        self.routes
-            .entry(path.to_string())
-            .or_insert_with(HashMap::new)
-            .insert(method, route_def);
+        Some(respond_through(req, &self.global_middlewares[..depth], response))
+    }
+
+    fn last_route_mut(&mut self, caller: &str) -> Option<&mut RouteDefinition> {
+        let table = Arc::make_mut(&mut self.table);
+        let (index, method, variant) = table
+            .last_route
+            .clone()
+            .unwrap_or_else(|| panic!("{} called before any route was registered", caller));
+        table.routes[index]
+            .methods
+            .get_mut(&method)
+            .and_then(|definitions| definitions.get_mut(variant))
+    }
+
+    // Lists all registered routes, sorted by host, pattern and method.
+    pub fn routes(&self) -> impl Iterator<Item = RouteInfo<'_>> {
+        self.table.routes()
+    }
+
+    pub fn global_middleware_count(&self) -> usize {
+        self.global_middlewares.len()
+    }
+
+    // Serves the route table on `path` (GET only) as plain text, or as JSON when the
+    // client asks for `application/json` or adds `?format=json`.
+    // Global middlewares still run, so the listing can be guarded like any other route.
+    // It takes precedence over routes with parameters; a fixed route on `path` panics.
+    pub fn enable_route_listing(&mut self, path: &str) -> &mut Self {
+        if self.table.has_static_route(path) {
+            panic!("Route conflict: {} is already registered and can't be the route listing", path);
+        }
+        Arc::make_mut(&mut self.table).route_listing_path = Some(path.to_string());
+        self
     }
 
     pub fn get<H>(&mut self, path: &str, handler: H) -> &mut Self
     where
         H: Handler + 'static,
     {
-        self.add_route_internal(HttpMethod::GET, path, handler, vec![]);
+        self.add_route_internal(HttpMethod::GET, path, None, handler, vec![]);
         self
     }
 
@@ -70,7 +310,7 @@ impl Router {
     where
         H: Handler + 'static,
     {
-        self.add_route_internal(HttpMethod::GET, path, handler, middlewares);
+        self.add_route_internal(HttpMethod::GET, path, None, handler, middlewares);
         self
     }
 
@@ -78,7 +318,7 @@ impl Router {
     where
         H: Handler + 'static,
     {
-        self.add_route_internal(HttpMethod::POST, path, handler, vec![]);
+        self.add_route_internal(HttpMethod::POST, path, None, handler, vec![]);
         self
     }
 
@@ -91,7 +331,7 @@ impl Router {
     where
         H: Handler + 'static,
     {
-        self.add_route_internal(HttpMethod::POST, path, handler, middlewares);
+        self.add_route_internal(HttpMethod::POST, path, None, handler, middlewares);
         self
     }
 
@@ -99,7 +339,7 @@ impl Router {
     where
         H: Handler + 'static,
     {
-        self.add_route_internal(HttpMethod::PUT, path, handler, vec![]);
+        self.add_route_internal(HttpMethod::PUT, path, None, handler, vec![]);
         self
     }
 
@@ -107,49 +347,313 @@ impl Router {
     where
         H: Handler + 'static,
     {
-        self.add_route_internal(HttpMethod::DELETE, path, handler, vec![]);
+        self.add_route_internal(HttpMethod::DELETE, path, None, handler, vec![]);
         self
     }
 
-    pub fn handle_request(&self, req: Request) -> Response {
-        match self.routes.get(&req.path) {
-            Some(methods_for_path) => {
-                match methods_for_path.get(&req.method) {
-                    Some(route_def) => {
-                        // Combine global and route specific middlewares
-                        let mut all_middlewares = self.global_middlewares.clone();
-                        all_middlewares.extend(route_def.middlewares.iter().cloned());
-
-                        middleware::dispatch_middleware_chain(
-                            req,
-                            Arc::new(all_middlewares),
-                            0,
-                            route_def.handler.clone(),
-                        )
-                    }
+    pub fn handle_request(&self, mut req: Request) -> Response {
+        req.extensions.extend(&self.state);
+        let table = self.table.clone();
+        let global_middleware_count = self.global_middlewares.len();
+
+        middleware::dispatch_middleware_chain(
+            req,
+            Arc::new(self.global_middlewares.clone()),
+            0,
+            Arc::new(move |req: Request| table.route(req, global_middleware_count)),
+        )
+    }
+}
+
+// The first of `middlewares` to turn away `req` before its body is sent, with its position.
+fn first_rejection(middlewares: &[Arc<dyn Middleware>], req: &Request) -> Option<(usize, Response)> {
+    middlewares
+        .iter()
+        .enumerate()
+        .find_map(|(index, middleware)| middleware.on_expect_continue(req).map(|response| (index, response)))
+}
+
+// Passes `response` back out through `middlewares` as if the handler behind them had
+// returned it.
+fn respond_through(req: Request, middlewares: &[Arc<dyn Middleware>], response: Response) -> Response {
+    let response = Mutex::new(Some(response));
+    middleware::dispatch_middleware_chain(
+        req,
+        Arc::new(middlewares.to_vec()),
+        0,
+        Arc::new(move |_: Request| {
+            // Only a middleware calling `next` twice gets here a second time.
+            response
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+                .unwrap_or_else(|| Response::new(500).with_body("500 Internal Server Error"))
+        }),
+    )
+}
+
+impl RouteTable {
+    fn routes(&self) -> impl Iterator<Item = RouteInfo<'_>> {
+        let host_routes = self.hosts.iter().flat_map(|(host, router)| {
+            router.routes().map(move |route| RouteInfo {
+                host: Some(host.as_str()),
+                ..route
+            })
+        });
+        let mut routes: Vec<RouteInfo<'_>> = self
+            .routes
+            .iter()
+            .flat_map(|entry| {
+                entry.methods.iter().flat_map(move |(method, definitions)| {
+                    definitions.iter().map(move |route_def| RouteInfo {
+                        host: None,
+                        method,
+                        pattern: entry.pattern.as_str(),
+                        produces: route_def.produces.as_deref(),
+                        name: route_def.name.as_deref(),
+                        middleware_count: route_def.middlewares.len(),
+                    })
+                })
+            })
+            .chain(host_routes)
+            .collect();
+        routes.sort_by(|a, b| {
+            a.host
+                .cmp(&b.host)
+                .then_with(|| a.pattern.cmp(b.pattern))
+                .then_with(|| a.method.to_string().cmp(&b.method.to_string()))
+                .then_with(|| a.produces.cmp(&b.produces))
+        });
+        routes.into_iter()
+    }
+
+    // Whether a route without parameters, here or on a host router, is registered on
+    // `path`. The route listing would shadow it.
+    fn has_static_route(&self, path: &str) -> bool {
+        self.routes
+            .iter()
+            .any(|entry| entry.pattern.is_static() && entry.pattern.matches(path).is_some())
+            || self.hosts.iter().any(|(_, router)| router.table.has_static_route(path))
+    }
+
+    fn select_host(&self, req: &Request) -> Option<(&Router, HashMap<String, String>)> {
+        let host = req.host()?;
+        self.hosts
+            .iter()
+            .filter_map(|(pattern, router)| pattern.matches(host).map(|captures| (pattern, router, captures)))
+            .min_by_key(|(pattern, _, _)| Reverse(pattern.specificity()))
+            .map(|(_, router, captures)| (router, captures))
+    }
+
+    fn on_expect_continue(&self, req: &Request) -> Option<Response> {
+        if let Some((host_router, _)) = self.select_host(req) {
+            return host_router.on_expect_continue(req);
+        }
+        let route_def = self.route_definition(req)?;
+        let (depth, response) = first_rejection(&route_def.middlewares, req)?;
+        Some(respond_through(req.clone(), &route_def.middlewares[..depth], response))
+    }
+
+    // The definition `req` would be dispatched to, if any.
+    fn route_definition(&self, req: &Request) -> Option<&RouteDefinition> {
+        if let Some((host_router, _)) = self.select_host(req) {
+            return host_router.table.route_definition(req);
+        }
+        match self.find_route(req) {
+            RouteMatch::Found(route_def, _) | RouteMatch::HeadFallback(route_def, _) => Some(route_def),
+            _ => None,
+        }
+    }
+
+    fn handle_route_listing(&self, req: Request, global_middleware_count: usize) -> Response {
+        let wants_json = req.query().get("format") == Some("json")
+            || req.negotiate(&["text/plain", "application/json"]) == Some("application/json");
+        let is_head = req.method == HttpMethod::HEAD;
+
+        if req.method != HttpMethod::GET && !is_head {
+            return Response::new(405).with_body("405 Method Not Allowed");
+        }
+
+        let (body, content_type) = if wants_json {
+            (self.render_routes_json(global_middleware_count), "application/json")
+        } else {
+            (self.render_routes_text(global_middleware_count), "text/plain")
+        };
+
+        let response = Response::new(200).with_header("Content-Type", content_type);
+        if is_head {
+            response
+        } else {
+            response.with_body(&body)
+        }
+    }
+
+    fn render_routes_text(&self, global_middleware_count: usize) -> String {
+        let mut out = format!("global middlewares: {}\n", global_middleware_count);
+        for route in self.routes() {
+            out.push_str(&format!(
+                "{:<8} {:<40} {:<20} {:<20} middlewares: {}\n",
+                route.method.to_string(),
+                format!("{}{}", route.host.unwrap_or(""), route.pattern),
+                route.produces.unwrap_or("*/*"),
+                route.name.unwrap_or("-"),
+                route.middleware_count
+            ));
+        }
+        out
+    }
+
+    fn render_routes_json(&self, global_middleware_count: usize) -> String {
+        let routes: Vec<String> = self
+            .routes()
+            .map(|route| {
+                let host = json_string_or_null(route.host);
+                let produces = json_string_or_null(route.produces);
+                let name = json_string_or_null(route.name);
+                format!(
+                    "{{\"host\":{},\"method\":\"{}\",\"pattern\":\"{}\",\"produces\":{},\"name\":{},\"middleware_count\":{}}}",
+                    host,
+                    escape_json(&route.method.to_string()),
+                    escape_json(route.pattern),
+                    produces,
+                    name,
+                    route.middleware_count
+                )
+            })
+            .collect();
+        format!(
+            "{{\"global_middleware_count\":{},\"routes\":[{}]}}",
+            global_middleware_count,
+            routes.join(",")
+        )
+    }
+
+    fn route(&self, req: Request, global_middleware_count: usize) -> Response {
+        if let Some(listing_path) = &self.route_listing_path {
+            let path = req.path.split('?').next().unwrap_or("");
+            if path == listing_path {
+                return self.handle_route_listing(req, global_middleware_count);
+            }
+        }
+
+        if let Some((host_router, captures)) = self.select_host(&req) {
+            let mut req = req;
+            req.params.extend(captures);
+            return host_router.handle_request(req);
+        }
+
+        match self.find_route(&req) {
+            RouteMatch::Found(route_def, params) => {
+                let mut req = req;
+                req.params.extend(params);
+                Self::dispatch(req, route_def)
+            }
+            RouteMatch::HeadFallback(route_def, params) => {
+                let mut req = req;
+                req.params.extend(params);
+                let mut response = Self::dispatch(req, route_def);
+                response.body.clear(); // Strip body for HEAD
+                response
+            }
+            RouteMatch::MethodNotAllowed => Response::new(405).with_body("405 Method Not Allowed"),
+            RouteMatch::NotAcceptable => Response::new(406).with_body("406 Not Acceptable"),
+            RouteMatch::NotFound => Response::new(404).with_body("404 NOT FOUND"),
+        }
+    }
+
+    // Static routes win over parameterized ones; otherwise routes are tried in registration
+    // order, so a request failing one route's constraints falls through to the next.
+    fn find_route(&self, req: &Request) -> RouteMatch<'_> {
+        let static_routes = self.routes.iter().filter(|entry| entry.pattern.is_static());
+        let param_routes = self.routes.iter().filter(|entry| !entry.pattern.is_static());
+
+        let mut path_matched = false;
+        let mut not_acceptable = false;
+        let mut head_fallback = None;
+        for entry in static_routes.chain(param_routes) {
+            let Some(params) = entry.pattern.matches(&req.path) else {
+                continue;
+            };
+            path_matched = true;
+
+            if let Some(definitions) = entry.methods.get(&req.method) {
+                match Self::select_variant(definitions, req) {
+                    Some(route_def) => return RouteMatch::Found(route_def, params),
                     None => {
-                        // Path exists, but not for this method
-                        if req.method == HttpMethod::HEAD && methods_for_path.contains_key(&HttpMethod::GET) {
-                            let get_route_def = methods_for_path.get(&HttpMethod::GET).unwrap();
-                            let mut all_middlewares = self.global_middlewares.clone();
-                            all_middlewares.extend(get_route_def.middlewares.iter().cloned());
-
-                            let mut response = middleware::dispatch_middleware_chain(
-                                req,
-                                Arc::new(all_middlewares),
-                                0,
-                                get_route_def.handler.clone(),
-                            );
-                            response.body = String::new(); // Strip body for HEAD
-                            response
-                        } else {
-                            Response::new(405)
-                                .with_body("405 Method Not Allowed")
-                        }
+                        not_acceptable = true;
+                        continue;
                     }
                 }
             }
-            None => Response::new(404).with_body("404 NOT FOUND")
+            if req.method == HttpMethod::HEAD && head_fallback.is_none() {
+                if let Some(definitions) = entry.methods.get(&HttpMethod::GET) {
+                    match Self::select_variant(definitions, req) {
+                        Some(get_route_def) => head_fallback = Some((get_route_def, params)),
+                        None => not_acceptable = true,
+                    }
+                }
+            }
+        }
+
+        match head_fallback {
+            Some((route_def, params)) => RouteMatch::HeadFallback(route_def, params),
+            None if not_acceptable => RouteMatch::NotAcceptable,
+            None if path_matched => RouteMatch::MethodNotAllowed,
+            None => RouteMatch::NotFound,
+        }
+    }
+
+    fn select_variant<'a>(definitions: &'a [RouteDefinition], req: &Request) -> Option<&'a RouteDefinition> {
+        let media_types: Vec<&str> = definitions.iter().filter_map(|def| def.produces.as_deref()).collect();
+        let fallback = definitions.iter().find(|def| def.produces.is_none());
+        if media_types.is_empty() {
+            return fallback;
+        }
+
+        match req.negotiate(&media_types) {
+            Some(chosen) => definitions.iter().find(|def| def.produces.as_deref() == Some(chosen)),
+            None => fallback,
+        }
+    }
+
+    fn dispatch(req: Request, route_def: &RouteDefinition) -> Response {
+        // Global middlewares already ran in `Router::handle_request`
+        let mut response = middleware::dispatch_middleware_chain(
+            req,
+            Arc::new(route_def.middlewares.clone()),
+            0,
+            route_def.handler.clone(),
+        );
+
+        if let Some(media_type) = &route_def.produces {
+            if !response.headers.contains("Content-Type") {
+                response.set_header("Content-Type", media_type);
+            }
+            response.append_header("Vary", "Accept");
+        }
+        response
+    }
+}
+
+fn json_string_or_null(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("\"{}\"", escape_json(value)),
+        None => "null".to_string(),
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
         }
     }
+    escaped
 }
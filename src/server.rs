@@ -1,24 +1,240 @@
 use rustls::pki_types::pem::PemObject;
 use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::collections::HashMap;
 use std::error::Error;
-use std::net::SocketAddr;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs::create_dir_all;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
 use tokio::signal;
-use tokio::sync::Notify;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
 use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
 use tokio_rustls::TlsAcceptor;
 
-use crate::{Logger, Request, Router};
+use crate::logger::RequestLine;
+use crate::middleware::RequestIds;
+use crate::multipart::TempFile;
+use crate::request::{find_head_end, RequestId, SpooledBody};
+use crate::{Logger, Request, Response, Router, TlsInfo};
+
+// Upper bound for the request line plus headers.
+const MAX_HEAD_SIZE: usize = 1024 * 8;
+
+// Deadlines for the phases of a request. Slow clients get 408 while the request is read;
+// a handler (including its middlewares) that misses its deadline gets 503, or the status
+// set with `handler_timeout_status`. TLS connections that don't finish the handshake in
+// time are closed. `None` disables a deadline.
+//
+// Handlers are synchronous and run on tokio's blocking pool, where they cannot be
+// cancelled: on timeout the response is sent right away, but the handler keeps its
+// thread until it returns and its result is dropped. `Limits::max_handlers` caps how
+// many can run at once, so hung handlers can't exhaust the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    tls_handshake: Option<Duration>,
+    read_head: Option<Duration>,
+    read_body: Option<Duration>,
+    handler: Option<Duration>,
+    write: Option<Duration>,
+    handler_timeout_status: u16,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timeouts {
+    // 10s each for the TLS handshake and the request head, 30s each for the body, the
+    // handler and the response.
+    pub fn new() -> Self {
+        Self {
+            tls_handshake: Some(Duration::from_secs(10)),
+            read_head: Some(Duration::from_secs(10)),
+            read_body: Some(Duration::from_secs(30)),
+            handler: Some(Duration::from_secs(30)),
+            write: Some(Duration::from_secs(30)),
+            handler_timeout_status: 503,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            tls_handshake: None,
+            read_head: None,
+            read_body: None,
+            handler: None,
+            write: None,
+            handler_timeout_status: 503,
+        }
+    }
+
+    pub fn tls_handshake(mut self, timeout: Option<Duration>) -> Self {
+        self.tls_handshake = timeout;
+        self
+    }
+
+    pub fn read_head(mut self, timeout: Option<Duration>) -> Self {
+        self.read_head = timeout;
+        self
+    }
+
+    pub fn read_body(mut self, timeout: Option<Duration>) -> Self {
+        self.read_body = timeout;
+        self
+    }
+
+    // The default for all routes; `Router::timeout` overrides it per route.
+    pub fn handler(mut self, timeout: Option<Duration>) -> Self {
+        self.handler = timeout;
+        self
+    }
+
+    pub fn write(mut self, timeout: Option<Duration>) -> Self {
+        self.write = timeout;
+        self
+    }
+
+    // 503 by default; 504 suits servers that mostly wait on upstream services.
+    pub fn handler_timeout_status(mut self, status: u16) -> Self {
+        self.handler_timeout_status = status;
+        self
+    }
+}
+
+// Caps that keep a flood of connections, or a few deliberately slow ones, from tying up
+// the server:
+//
+// - `max_connections`: open connections at once. When full the server stops accepting
+//   and new clients wait in the OS backlog, or with `reject_when_full` get 503 at once.
+// - `max_connections_per_ip`: open connections per client address. Extra ones get 429.
+//   Off by default, as many clients can share an address behind NAT or a proxy.
+// - `max_body_size`: request body size. Larger requests get 413 as soon as their head is
+//   read, so their body is never buffered and `Expect: 100-continue` clients are turned
+//   away before sending it. `Router::max_body_size` overrides it per route.
+// - `min_data_rate`: after a grace period, clients must keep sending the request at an
+//   average of at least this many bytes per second or get 408. This catches slowloris
+//   clients that trickle bytes just fast enough to dodge the read timeouts.
+// - `spool_multipart_over`: `multipart/form-data` bodies larger than this are written to
+//   a temp file as they arrive rather than held in memory (see `Request::body_reader`).
+//
+// Rejected TLS connections are closed without a response, before the handshake.
+//
+//     Server::new(addr).with_limits(Limits::new().max_connections_per_ip(Some(32)));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    max_connections: Option<usize>,
+    max_connections_per_ip: Option<usize>,
+    reject_when_full: bool,
+    max_body_size: Option<usize>,
+    min_data_rate: Option<MinDataRate>,
+    max_handlers: Option<usize>,
+    spool_multipart_over: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MinDataRate {
+    bytes_per_second: u64,
+    grace: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Limits {
+    // 10,000 connections with backpressure, no per-IP cap, bodies up to 10 MiB, at least
+    // 240 bytes per second after a 5s grace period, 256 handlers running at once, and
+    // multipart bodies over 1 MiB spooled to disk.
+    pub fn new() -> Self {
+        Self {
+            max_connections: Some(10_000),
+            max_connections_per_ip: None,
+            reject_when_full: false,
+            max_body_size: Some(10 * 1024 * 1024),
+            min_data_rate: Some(MinDataRate {
+                bytes_per_second: 240,
+                grace: Duration::from_secs(5),
+            }),
+            max_handlers: Some(256),
+            spool_multipart_over: Some(1024 * 1024),
+        }
+    }
+
+    // Spooling stays on: it bounds memory, not what clients may send.
+    pub fn unlimited() -> Self {
+        Self {
+            max_connections: None,
+            max_connections_per_ip: None,
+            reject_when_full: false,
+            max_body_size: None,
+            min_data_rate: None,
+            max_handlers: None,
+            spool_multipart_over: Some(1024 * 1024),
+        }
+    }
+
+    pub fn max_connections(mut self, max: Option<usize>) -> Self {
+        self.max_connections = max;
+        self
+    }
+
+    pub fn max_connections_per_ip(mut self, max: Option<usize>) -> Self {
+        self.max_connections_per_ip = max;
+        self
+    }
+
+    // Answer 503 instead of waiting for a free slot once `max_connections` is reached.
+    pub fn reject_when_full(mut self, reject: bool) -> Self {
+        self.reject_when_full = reject;
+        self
+    }
+
+    // In bytes; the default for all routes. Checked against `Content-Length` before the
+    // body is read. Bodies without a usable length, such as chunked uploads, are refused
+    // beforehand, so no body can be read past the limit.
+    pub fn max_body_size(mut self, max: Option<usize>) -> Self {
+        self.max_body_size = max;
+        self
+    }
+
+    // Handlers running at once, counting ones that timed out but haven't returned yet.
+    // Requests beyond it get 503 right away. Keep it below the size of tokio's blocking
+    // pool (512 threads by default), which the server also uses for other work.
+    pub fn max_handlers(mut self, max: Option<usize>) -> Self {
+        self.max_handlers = max;
+        self
+    }
+
+    // In bytes. `None` keeps every body in memory.
+    pub fn spool_multipart_over(mut self, size: Option<usize>) -> Self {
+        self.spool_multipart_over = size;
+        self
+    }
+
+    // A rate of 0 turns the check off.
+    pub fn min_data_rate(mut self, bytes_per_second: u64, grace: Duration) -> Self {
+        self.min_data_rate = (bytes_per_second > 0).then_some(MinDataRate { bytes_per_second, grace });
+        self
+    }
+}
 
 pub struct Server {
     address: String,
     router: Router,
     logger: Option<Logger>,
     tls_config: Option<Arc<ServerConfig>>,
+    timeouts: Timeouts,
+    limits: Limits,
+    request_ids: Option<RequestIds>,
 }
 
 impl Server {
@@ -28,9 +244,30 @@ impl Server {
             router: Router::new(),
             logger: None,
             tls_config: None,
+            timeouts: Timeouts::new(),
+            limits: Limits::new(),
+            request_ids: None,
         }
     }
 
+    // Assigns request IDs before the router runs, so the responses the server makes
+    // itself (handler timeouts, overload, panics) carry one too and their error log lines
+    // can be matched up. See `RequestIds`.
+    pub fn with_request_ids(mut self, request_ids: RequestIds) -> Self {
+        self.request_ids = Some(request_ids);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
     pub fn with_router(mut self, router: Router) -> Self {
         self.router = router;
         self
@@ -39,12 +276,14 @@ impl Server {
     pub async fn with_logging(mut self) -> Self {
         let log_dir = PathBuf::from("logs");
         let log_file_path = log_dir.join("access.log");
+        let error_log_path = log_dir.join("error.log");
 
         if !log_dir.exists() {
             create_dir_all(&log_dir).await.unwrap();
         }
 
-        self.logger = Some(Logger::new(log_file_path.to_str().unwrap()).await.unwrap());
+        let logger = Logger::new(log_file_path.to_str().unwrap()).await.unwrap();
+        self.logger = Some(logger.with_error_log(error_log_path.to_str().unwrap()).await.unwrap());
         self
     }
 
@@ -93,22 +332,59 @@ impl Server {
             }
         }
 
+        let slots = ConnectionSlots::new(&self.limits);
+        let rejections = Arc::new(Semaphore::new(MAX_PENDING_REJECTIONS));
+        let context = Context {
+            router: self.router.clone(),
+            logger: self.logger.clone(),
+            timeouts: self.timeouts,
+            limits: self.limits,
+            handlers: HandlerSlots::new(&self.limits),
+            request_ids: self.request_ids.clone(),
+        };
+
         loop {
             tokio::select! {
-                Ok((stream, client_addr)) = listener.accept() => {
-                    let router = self.router.clone();
-                    let logger = self.logger.clone();
+                Ok((stream, client_addr, admission)) = slots.accept(&listener) => {
+                    let context = context.clone();
+                    let timeouts = self.timeouts;
+
+                    let slot = match admission {
+                        Admission::Admitted(slot) => slot,
+                        Admission::Rejected(status) => {
+                            if self.tls_config.is_none() {
+                                reject(stream, status, &rejections);
+                            }
+                            continue;
+                        }
+                    };
+
+                    let conn = ConnectionInfo {
+                        remote_addr: client_addr,
+                        local_addr: stream.local_addr().ok(),
+                        tls: None,
+                    };
 
                     if let Some(tls_config) = self.tls_config.clone() {
                         let acceptor = TlsAcceptor::from(tls_config);
 
                         tokio::spawn(async move {
-                            let tls_stream = acceptor.accept(stream).await.unwrap();
-                            handle_connection(tls_stream, router, client_addr, logger).await;
+                            let _slot = slot;
+                            let tls_stream = match with_deadline(timeouts.tls_handshake, acceptor.accept(stream)).await {
+                                Ok(tls_stream) => tls_stream,
+                                // Failed or abandoned handshakes are the client's problem.
+                                Err(_) => return,
+                            };
+                            let conn = ConnectionInfo {
+                                tls: Some(tls_info(&tls_stream)),
+                                ..conn
+                            };
+                            handle_connection(tls_stream, conn, context).await;
                         });
                     } else {
                         tokio::spawn(async move {
-                            handle_connection(stream, router, client_addr, logger).await;
+                            let _slot = slot;
+                            handle_connection(stream, conn, context).await;
                         });
                     }
                 }
@@ -127,34 +403,581 @@ impl Server {
     } // run()
 }
 
-async fn handle_connection<T: AsyncReadExt + AsyncWriteExt + Unpin>(
-    mut stream: T,
+// What every connection gets from the server.
+#[derive(Clone)]
+struct Context {
     router: Router,
-    client_addr: SocketAddr,
     logger: Option<Logger>,
+    timeouts: Timeouts,
+    limits: Limits,
+    handlers: HandlerSlots,
+    request_ids: Option<RequestIds>,
+}
+
+// Per-connection details copied onto every request read from the connection.
+struct ConnectionInfo {
+    remote_addr: SocketAddr,
+    local_addr: Option<SocketAddr>,
+    tls: Option<TlsInfo>,
+}
+
+enum Admission {
+    Admitted(ConnectionSlot),
+    Rejected(u16),
+}
+
+// Counts open connections against `Limits`, globally and per client address.
+struct ConnectionSlots {
+    global: Option<Arc<Semaphore>>,
+    reject_when_full: bool,
+    max_per_ip: Option<usize>,
+    per_ip: IpCounts,
+}
+
+type IpCounts = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+// Held for the lifetime of a connection; frees its slots on drop.
+struct ConnectionSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+    ip: Option<(IpAddr, IpCounts)>,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        if let Some((ip, per_ip)) = &self.ip {
+            let mut per_ip = per_ip.lock().unwrap();
+            if let Some(count) = per_ip.get_mut(ip) {
+                *count -= 1;
+                if *count == 0 {
+                    per_ip.remove(ip);
+                }
+            }
+        }
+    }
+}
+
+impl ConnectionSlots {
+    fn new(limits: &Limits) -> Self {
+        Self {
+            global: limits.max_connections.map(|max| Arc::new(Semaphore::new(max))),
+            reject_when_full: limits.reject_when_full,
+            max_per_ip: limits.max_connections_per_ip,
+            per_ip: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn accept(&self, listener: &TcpListener) -> std::io::Result<(TcpStream, SocketAddr, Admission)> {
+        // Backpressure: don't accept until a slot is free.
+        let waited = match &self.global {
+            Some(global) if !self.reject_when_full => {
+                Some(global.clone().acquire_owned().await.expect("connection semaphore closed"))
+            }
+            _ => None,
+        };
+        let (stream, client_addr) = listener.accept().await?;
+
+        let permit = match (waited, &self.global) {
+            (Some(permit), _) => Some(permit),
+            (None, Some(global)) => match global.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return Ok((stream, client_addr, Admission::Rejected(503))),
+            },
+            (None, None) => None,
+        };
+
+        let ip = match self.max_per_ip {
+            Some(max) => {
+                let ip = client_addr.ip();
+                let mut per_ip = self.per_ip.lock().unwrap();
+                let count = per_ip.entry(ip).or_insert(0);
+                if *count >= max {
+                    return Ok((stream, client_addr, Admission::Rejected(429)));
+                }
+                *count += 1;
+                Some((ip, self.per_ip.clone()))
+            }
+            None => None,
+        };
+
+        let slot = ConnectionSlot { _permit: permit, ip };
+        Ok((stream, client_addr, Admission::Admitted(slot)))
+    }
+}
+
+// Refused connections still being answered. Past this, they are closed without a
+// response, so a flood of refused connections can't tie up tasks and sockets.
+const MAX_PENDING_REJECTIONS: usize = 64;
+// How long a refused client gets to take its response, whatever `Timeouts::write` says.
+const REJECTION_WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Answers a connection refused by `Limits` with `status` if a rejection slot is free, and
+// closes it either way.
+fn reject(mut stream: TcpStream, status: u16, rejections: &Arc<Semaphore>) {
+    let Ok(permit) = rejections.clone().try_acquire_owned() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let _permit = permit;
+        let response = Response::new(status);
+        let body = format!("{} {}", status, response.reason_phrase);
+        let response = response.with_header("Retry-After", "1").with_body(&body);
+        // No draining: the request, if any, is dropped with the connection.
+        if with_deadline(Some(REJECTION_WRITE_TIMEOUT), stream.write_all(&response.to_bytes())).await.is_ok() {
+            let _ = tokio::time::timeout(REJECTION_WRITE_TIMEOUT, stream.shutdown()).await;
+        }
+    });
+}
+
+// Sends a response to a client that may still be sending its request.
+async fn send_and_close<T: AsyncReadExt + AsyncWriteExt + Unpin>(
+    stream: &mut T,
+    response: &Response,
+    timeouts: &Timeouts,
 ) {
-    let mut buffer = [0u8; 1024 * 8];
-    match stream.read(&mut buffer).await {
-        Ok(_) => {
-            let request = Request::from_buffer(&buffer).unwrap();
+    if with_deadline(timeouts.write, stream.write_all(&response.to_bytes())).await.is_err() {
+        return;
+    }
 
-            let response = router.handle_request(request.clone());
+    // Closing with the request still unread makes the OS send a reset, which can destroy
+    // the response before the client reads it. Drain briefly first.
+    let _ = stream.shutdown().await;
+    let mut sink = [0u8; 1024];
+    let drain = async { while matches!(stream.read(&mut sink).await, Ok(n) if n > 0) {} };
+    let _ = tokio::time::timeout(Duration::from_secs(1), drain).await;
+}
 
-            let _ = stream.write_all(response.to_bytes().as_slice()).await;
+fn tls_info<IO>(stream: &TlsStream<IO>) -> TlsInfo {
+    let (_, session) = stream.get_ref();
+    TlsInfo {
+        version: session
+            .protocol_version()
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        cipher_suite: session
+            .negotiated_cipher_suite()
+            .and_then(|suite| suite.suite().as_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        alpn_protocol: session
+            .alpn_protocol()
+            .map(|proto| String::from_utf8_lossy(proto).into_owned()),
+        server_name: session.server_name().map(str::to_string),
+    }
+}
 
-            if let Some(logger) = logger {
-                let ip = client_addr.to_string();
-                let method = request.method;
-                let path = request.path;
-                let version = request.version;
-                let status = response.status_code;
-                let size = response.body.len();
+enum ReadError {
+    Closed,
+    Malformed,
+    HeadTooLarge,
+    BodyTooLarge,
+    // `Transfer-Encoding: chunked`, which isn't supported yet.
+    LengthRequired,
+    // Any other transfer coding.
+    UnsupportedTransferEncoding,
+    ExpectationFailed,
+    // A middleware turned away an `Expect: 100-continue` request before its body was sent.
+    Rejected(Box<(Request, Response)>),
+    TimedOut,
+    Io(std::io::Error),
+}
 
-                let log_entry = format!("{ip} - \"{method} {path} {version}\" {status} {size}\n");
+// Counts handlers on the blocking pool against `Limits::max_handlers`. A handler holds
+// its slot until it returns, even after its request timed out.
+#[derive(Clone)]
+struct HandlerSlots(Option<Arc<Semaphore>>);
+
+impl HandlerSlots {
+    fn new(limits: &Limits) -> Self {
+        Self(limits.max_handlers.map(|max| Arc::new(Semaphore::new(max))))
+    }
+
+    // `Err` when all slots are taken.
+    fn try_acquire(&self) -> Result<Option<OwnedSemaphorePermit>, ()> {
+        match &self.0 {
+            Some(semaphore) => semaphore.clone().try_acquire_owned().map(Some).map_err(|_| ()),
+            None => Ok(None),
+        }
+    }
+}
 
-                logger.log(log_entry.as_str()).await;
+// Runs the router on the blocking pool under the route's (or the server's) deadline.
+async fn run_handler(router: &Router, request: Request, timeouts: &Timeouts, handlers: &HandlerSlots) -> Response {
+    let deadline = router.route_timeout(&request).or(timeouts.handler);
+    let router = router.clone();
+    run_blocking(deadline, timeouts, handlers, move || router.handle_request(request))
+        .await
+        .unwrap_or_else(|response| response)
+}
+
+// Like `read_body`, but into a temp file that is removed again if reading fails.
+async fn spool_body<T: AsyncReadExt + Unpin>(
+    stream: &mut T,
+    mut received: Vec<u8>,
+    content_length: usize,
+    rate: &mut DataRate,
+) -> Result<TempFile, ReadError> {
+    let (temp, file) = tokio::task::spawn_blocking(|| TempFile::create(&std::env::temp_dir()))
+        .await
+        .map_err(|e| ReadError::Io(e.into()))??;
+    let mut file = tokio::fs::File::from_std(file);
+    received.truncate(content_length);
+    file.write_all(&received).await?;
+    let mut written = received.len();
+    let mut chunk = [0u8; 1024 * 8];
+    while written < content_length {
+        let n = rate.read(stream, &mut chunk).await?;
+        let n = n.min(content_length - written);
+        file.write_all(&chunk[..n]).await?;
+        written += n;
+    }
+    file.flush().await?;
+    Ok(temp)
+}
+
+// Lets the middlewares veto an `Expect: 100-continue` request. They may block (password
+// hashing, key lookups), so they run like handlers: on the blocking pool, in a handler
+// slot and under the same deadline.
+async fn check_expect_continue(
+    router: &Router,
+    request: &Request,
+    timeouts: &Timeouts,
+    handlers: &HandlerSlots,
+) -> Option<Response> {
+    let deadline = router.route_timeout(request).or(timeouts.handler);
+    let router = router.clone();
+    let request = request.clone();
+    run_blocking(deadline, timeouts, handlers, move || router.on_expect_continue(&request))
+        .await
+        .unwrap_or_else(Some)
+}
+
+// Runs `f` on the blocking pool in a handler slot, giving up on it after `deadline`.
+// `Err` is the response to send when it couldn't run or didn't finish.
+async fn run_blocking<F, T>(
+    deadline: Option<Duration>,
+    timeouts: &Timeouts,
+    handlers: &HandlerSlots,
+    f: F,
+) -> Result<T, Response>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let Ok(permit) = handlers.try_acquire() else {
+        return Err(Response::new(503)
+            .with_header("Retry-After", "1")
+            .with_body("503 Service Unavailable: too many requests in progress"));
+    };
+    let task = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    });
+
+    let result = match deadline {
+        Some(deadline) => match tokio::time::timeout(deadline, task).await {
+            Ok(result) => result,
+            Err(_) => {
+                let status = timeouts.handler_timeout_status;
+                let response = Response::new(status);
+                let body = format!("{} {}", status, response.reason_phrase);
+                return Err(response.with_body(&body));
             }
+        },
+        None => task.await,
+    };
+    // A panicking handler only fails its own request.
+    result.map_err(|_| Response::new(500).with_body("500 Internal Server Error"))
+}
+
+// Awaits `future`, failing with `TimedOut` once `limit` has passed.
+async fn with_deadline<F, T, E>(limit: Option<Duration>, future: F) -> Result<T, ReadError>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<ReadError>,
+{
+    let result = match limit {
+        Some(limit) => tokio::time::timeout(limit, future)
+            .await
+            .map_err(|_| ReadError::TimedOut)?,
+        None => future.await,
+    };
+    result.map_err(Into::into)
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(e: std::io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+// Tracks the bytes received for a request against `Limits::min_data_rate`.
+struct DataRate {
+    min: Option<MinDataRate>,
+    start: Instant,
+    received: u64,
+}
+
+impl DataRate {
+    fn new(min: Option<MinDataRate>) -> Self {
+        Self {
+            min,
+            start: Instant::now(),
+            received: 0,
+        }
+    }
+
+    // The point by which more data must arrive to keep up the minimum rate.
+    fn deadline(&self) -> Option<Instant> {
+        let min = self.min?;
+        let allowed = Duration::from_secs_f64(self.received as f64 / min.bytes_per_second as f64);
+        Some(self.start + min.grace + allowed)
+    }
+
+    async fn read<T: AsyncReadExt + Unpin>(&mut self, stream: &mut T, chunk: &mut [u8]) -> Result<usize, ReadError> {
+        let n = match self.deadline() {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), stream.read(chunk))
+                .await
+                .map_err(|_| ReadError::TimedOut)??,
+            None => stream.read(chunk).await?,
+        };
+        if n == 0 {
+            return Err(ReadError::Closed);
         }
-        Err(e) => eprintln!("[!] Read error: {}", e),
+        self.received += n as u64;
+        Ok(n)
+    }
+}
+
+// Reads until the end of the request head. Returns the head and any body bytes that
+// arrived with it.
+async fn read_head<T: AsyncReadExt + Unpin>(
+    stream: &mut T,
+    rate: &mut DataRate,
+) -> Result<(Vec<u8>, Vec<u8>), ReadError> {
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024 * 8];
+    loop {
+        if let Some(end) = find_head_end(&buffer) {
+            let rest = buffer.split_off(end);
+            return Ok((buffer, rest));
+        }
+        if buffer.len() >= MAX_HEAD_SIZE {
+            return Err(ReadError::HeadTooLarge);
+        }
+
+        let n = rate.read(stream, &mut chunk).await?;
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+}
+
+// Reads the rest of a `Content-Length` delimited body. The length has been checked
+// against the body size limit, so no more than that is ever read.
+async fn read_body<T: AsyncReadExt + Unpin>(
+    stream: &mut T,
+    mut body: Vec<u8>,
+    content_length: usize,
+    rate: &mut DataRate,
+) -> Result<Vec<u8>, ReadError> {
+    body.truncate(content_length);
+    let mut chunk = [0u8; 1024 * 8];
+    while body.len() < content_length {
+        let n = rate.read(stream, &mut chunk).await?;
+        let remaining = content_length - body.len();
+        body.extend_from_slice(&chunk[..n.min(remaining)]);
+    }
+    Ok(body)
+}
+
+// The body length declared by `Content-Length`. Transfer codings aren't supported yet, so
+// their bodies are refused rather than read as empty; so is a length that doesn't parse
+// or disagrees with another `Content-Length`.
+fn body_length(request: &Request) -> Result<u64, ReadError> {
+    if let Some(codings) = request.header("Transfer-Encoding") {
+        let chunked = codings.split(',').any(|coding| coding.trim().eq_ignore_ascii_case("chunked"));
+        return Err(if chunked {
+            ReadError::LengthRequired
+        } else {
+            ReadError::UnsupportedTransferEncoding
+        });
+    }
+
+    let mut length = None;
+    for value in request.headers.get_all("Content-Length").flat_map(|value| value.split(',')) {
+        let value = value.trim();
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ReadError::Malformed);
+        }
+        let value: u64 = value.parse().map_err(|_| ReadError::Malformed)?;
+        if length.is_some_and(|length| length != value) {
+            return Err(ReadError::Malformed);
+        }
+        length = Some(value);
+    }
+    Ok(length.unwrap_or(0))
+}
+
+async fn read_request<T: AsyncReadExt + AsyncWriteExt + Unpin>(
+    stream: &mut T,
+    conn: &ConnectionInfo,
+    context: &Context,
+) -> Result<Request, ReadError> {
+    let Context {
+        router,
+        timeouts,
+        limits,
+        handlers,
+        ..
+    } = context;
+    let mut rate = DataRate::new(limits.min_data_rate);
+    let (head, rest) = with_deadline(timeouts.read_head, read_head(stream, &mut rate)).await?;
+    let mut request = Request::from_buffer(&head)
+        .map_err(|_| ReadError::Malformed)?
+        .with_remote_addr(conn.remote_addr);
+    request.local_addr = conn.local_addr;
+    request.tls = conn.tls.clone();
+    if let Some(logger) = &context.logger {
+        request.extensions.insert(logger.clone());
+    }
+    if let Some(request_ids) = &context.request_ids {
+        request_ids.assign(&mut request);
+    }
+    let content_length = body_length(&request)?;
+
+    // `100-continue` is the only expectation defined.
+    let expect = request.header("Expect").map(|expect| expect.trim().to_ascii_lowercase());
+    if expect.as_deref().is_some_and(|expect| expect != "100-continue") {
+        return Err(ReadError::ExpectationFailed);
+    }
+
+    // `body_length` refused everything without a declared length, and `read_body` reads
+    // exactly that many bytes, so this check bounds the bytes actually read.
+    let max_body_size = router.route_max_body_size(&request).or(limits.max_body_size);
+    if max_body_size.is_some_and(|max| content_length > max as u64) {
+        return Err(ReadError::BodyTooLarge);
+    }
+
+    let content_length = content_length as usize;
+    // HTTP/1.0 clients don't know `100 Continue` and must have it ignored.
+    if expect.is_some() && request.version != "HTTP/1.0" {
+        if let Some(response) = check_expect_continue(router, &request, timeouts, handlers).await {
+            return Err(ReadError::Rejected(Box::new((request, response))));
+        }
+        // Clients may stop waiting and send the body anyway.
+        if rest.len() < content_length {
+            with_deadline(timeouts.write, stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")).await?;
+        }
+    }
+
+    if should_spool(&request, content_length, limits) {
+        let body = spool_body(stream, rest, content_length, &mut rate);
+        let file = with_deadline(timeouts.read_body, body).await?;
+        request.extensions.insert(SpooledBody(file));
+    } else {
+        let body = read_body(stream, rest, content_length, &mut rate);
+        request.body = with_deadline(timeouts.read_body, body).await?;
+    }
+    Ok(request)
+}
+
+// Large uploads go to disk. Encoded bodies stay in memory for `Decompression`.
+fn should_spool(request: &Request, content_length: usize, limits: &Limits) -> bool {
+    limits.spool_multipart_over.is_some_and(|max| content_length > max)
+        && !request.headers.contains("Content-Encoding")
+        && request
+            .headers
+            .mime_type()
+            .is_some_and(|mime| mime == "multipart/form-data")
+}
+
+async fn handle_connection<T: AsyncReadExt + AsyncWriteExt + Unpin>(mut stream: T, conn: ConnectionInfo, context: Context) {
+    let client_addr = conn.remote_addr;
+    let Context {
+        router,
+        logger,
+        timeouts,
+        handlers,
+        ..
+    } = &context;
+    match read_request(&mut stream, &conn, &context).await {
+        Ok(request) => {
+            let line = RequestLine::from(&request);
+            let mut response = run_handler(router, request, timeouts, handlers).await;
+            stamp_request_id(&context, &line, &mut response);
+
+            let bytes = response.to_bytes();
+            let _ = with_deadline(timeouts.write, stream.write_all(&bytes)).await;
+            log_exchange(logger.as_ref(), &line, &response).await;
+        }
+        Err(ReadError::Malformed) => {
+            let response = Response::new(400).with_body("400 Bad Request");
+            send_and_close(&mut stream, &response, timeouts).await;
+        }
+        Err(ReadError::HeadTooLarge) => {
+            let response = Response::new(431).with_body("431 Request Header Fields Too Large");
+            send_and_close(&mut stream, &response, timeouts).await;
+        }
+        Err(ReadError::BodyTooLarge) => {
+            let response = Response::new(413).with_body("413 Payload Too Large");
+            send_and_close(&mut stream, &response, timeouts).await;
+        }
+        Err(ReadError::LengthRequired) => {
+            let response = Response::new(411).with_body("411 Length Required: chunked request bodies are not supported");
+            send_and_close(&mut stream, &response, timeouts).await;
+        }
+        Err(ReadError::UnsupportedTransferEncoding) => {
+            let response = Response::new(501).with_body("501 Not Implemented: unsupported Transfer-Encoding");
+            send_and_close(&mut stream, &response, timeouts).await;
+        }
+        Err(ReadError::ExpectationFailed) => {
+            let response = Response::new(417).with_body("417 Expectation Failed");
+            send_and_close(&mut stream, &response, timeouts).await;
+        }
+        Err(ReadError::Rejected(rejection)) => {
+            let (request, mut response) = *rejection;
+            let line = RequestLine::from(&request);
+            stamp_request_id(&context, &line, &mut response);
+            send_and_close(&mut stream, &response, timeouts).await;
+            log_exchange(logger.as_ref(), &line, &response).await;
+        }
+        Err(ReadError::TimedOut) => {
+            let response = Response::new(408).with_body("408 Request Timeout");
+            let _ = with_deadline(timeouts.write, stream.write_all(&response.to_bytes())).await;
+        }
+        Err(ReadError::Closed) => {}
+        Err(ReadError::Io(e)) => match logger {
+            Some(logger) => logger.error(None, &format!("read error from {}: {}", client_addr, e)).await,
+            None => eprintln!("[!] Read error: {}", e),
+        },
+    }
+}
+
+// Echoes the ID the server assigned, also on responses that never went through the
+// middlewares.
+fn stamp_request_id(context: &Context, request: &RequestLine, response: &mut Response) {
+    if let (Some(request_ids), Some(id)) = (&context.request_ids, &request.request_id) {
+        request_ids.stamp(response, id);
+    }
+}
+
+// Writes the access log line for a request that got a response, and an error log line
+// if the server failed it.
+async fn log_exchange(logger: Option<&Logger>, request: &RequestLine, response: &Response) {
+    let Some(logger) = logger else {
+        return;
+    };
+    logger.access_line(request, response).await;
+    if response.status_code >= 500 {
+        let request_id = response
+            .extensions
+            .get::<RequestId>()
+            .or(request.request_id.as_ref())
+            .map(RequestId::as_str);
+        let client_addr = request.remote_addr.map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
+        let message = format!(
+            "{} {} from {} failed with {}",
+            request.method, request.path, client_addr, response.status_code
+        );
+        logger.error(request_id, &message).await;
     }
 }
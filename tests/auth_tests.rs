@@ -0,0 +1,139 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rust_http_server::middleware::auth::constant_time_eq;
+use rust_http_server::middleware::{BasicAuth, BearerAuth, Htpasswd, Principal};
+use rust_http_server::{Request, Response, Router};
+
+fn whoami(req: Request) -> Response {
+    let principal = req.extension::<Principal>().expect("principal attached");
+    Response::new(200).with_body(&format!("{} via {}", principal.name, principal.scheme))
+}
+
+fn router_with(auth: impl rust_http_server::Middleware + 'static) -> Router {
+    let mut router = Router::new();
+    router.use_global(auth);
+    router.get("/me", whoami);
+    router
+}
+
+fn basic(user: &str, password: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)))
+}
+
+#[test]
+fn test_basic_auth_challenges_and_accepts() {
+    let router = router_with(BasicAuth::new("admin area", |user: &str, password: &str| {
+        user == "alice" && password == "wonderland"
+    }));
+
+    let response = router.handle_request(Request::get("/me"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(
+        response.headers.get("WWW-Authenticate"),
+        Some("Basic realm=\"admin area\", charset=\"UTF-8\"")
+    );
+
+    let response = router.handle_request(Request::get("/me").with_header("Authorization", &basic("alice", "nope")));
+    assert_eq!(response.status_code, 401);
+
+    let response = router.handle_request(Request::get("/me").with_header("Authorization", "Basic not-base64!"));
+    assert_eq!(response.status_code, 401);
+
+    let response =
+        router.handle_request(Request::get("/me").with_header("Authorization", &basic("alice", "wonderland")));
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body_text(), "alice via Basic");
+}
+
+#[test]
+fn test_htpasswd_bcrypt_and_sha_entries() {
+    let bcrypt_hash = bcrypt::hash("hunter2", 4).unwrap().replacen("$2b$", "$2y$", 1);
+    let file = format!(
+        "# users\nbob:{}\ncarol:{{SHA}}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n\ndave:$apr1$abc$def\n",
+        bcrypt_hash
+    );
+    let htpasswd = Htpasswd::parse(&file);
+    assert_eq!(htpasswd.len(), 3);
+
+    let router = router_with(BasicAuth::new("files", htpasswd));
+    let status = |user: &str, password: &str| {
+        router
+            .handle_request(Request::get("/me").with_header("Authorization", &basic(user, password)))
+            .status_code
+    };
+    assert_eq!(status("bob", "hunter2"), 200);
+    assert_eq!(status("bob", "hunter3"), 401);
+    assert_eq!(status("carol", "password"), 200);
+    assert_eq!(status("carol", "Password"), 401);
+    assert_eq!(status("dave", "anything"), 401);
+    assert_eq!(status("erin", "hunter2"), 401);
+}
+
+#[test]
+fn test_htpasswd_unknown_users_are_not_faster() {
+    use rust_http_server::middleware::auth::CredentialVerifier;
+    use std::time::{Duration, Instant};
+
+    let htpasswd = Htpasswd::parse(&format!("bob:{}\n", bcrypt::hash("hunter2", 8).unwrap()));
+    let time = |user: &str| {
+        let started = Instant::now();
+        assert!(!htpasswd.verify(user, "wrong"));
+        started.elapsed()
+    };
+    let known = (0..3).map(|_| time("bob")).min().unwrap();
+    let unknown = (0..3).map(|_| time("mallory")).min().unwrap();
+    assert!(unknown * 2 > known, "unknown {:?} vs known {:?}", unknown, known);
+    assert!(known > Duration::from_millis(1));
+}
+
+#[test]
+fn test_bearer_auth_challenges() {
+    let router = router_with(BearerAuth::with_tokens(&[("t0ken", "deploy-bot")]).realm("api"));
+
+    let response = router.handle_request(Request::get("/me"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(response.headers.get("WWW-Authenticate"), Some("Bearer realm=\"api\""));
+
+    let response = router.handle_request(Request::get("/me").with_header("Authorization", "Bearer wrong"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(
+        response.headers.get("WWW-Authenticate"),
+        Some("Bearer realm=\"api\", error=\"invalid_token\"")
+    );
+
+    let response = router.handle_request(Request::get("/me").with_header("Authorization", "bearer t0ken"));
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body_text(), "deploy-bot via Bearer");
+}
+
+#[test]
+fn test_bearer_auth_custom_validator() {
+    let router = router_with(BearerAuth::new(|token: &str| {
+        token.strip_prefix("user-").map(|name| Principal::new(name, "Bearer"))
+    }));
+
+    let response = router.handle_request(Request::get("/me").with_header("Authorization", "Bearer user-42"));
+    assert_eq!(response.body_text(), "42 via Bearer");
+
+    let response = router.handle_request(Request::get("/me").with_header("Authorization", "Bearer admin"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(response.headers.get("WWW-Authenticate"), Some("Bearer error=\"invalid_token\""));
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"secret", b"secret"));
+    assert!(!constant_time_eq(b"secret", b"secreT"));
+    assert!(!constant_time_eq(b"secret", b"secrets"));
+}
+
+#[test]
+fn test_auth_rejects_expect_continue_early() {
+    let router = router_with(BearerAuth::with_tokens(&[("s3cr3t", "deploy-bot")]));
+
+    let response = router.on_expect_continue(&Request::post("/me"));
+    assert_eq!(response.map(|r| r.status_code), Some(401));
+
+    let request = Request::post("/me").with_header("Authorization", "Bearer s3cr3t");
+    assert!(router.on_expect_continue(&request).is_none());
+}
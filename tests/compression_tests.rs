@@ -0,0 +1,136 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use rust_http_server::middleware::{Compression, Decompression};
+use rust_http_server::{Request, Response, Router};
+use std::io::{Read, Write};
+
+fn router() -> Router {
+    let mut router = Router::new();
+    router.use_global(Compression::new().min_size(64));
+    router.get("/json", |_| {
+        Response::new(200)
+            .with_body(&"{\"key\":\"value\"},".repeat(100))
+            .with_header("Content-Type", "application/json")
+    });
+    router.get("/small", |_| Response::new(200).with_body("tiny"));
+    router.get("/tagged", |_| {
+        Response::new(200)
+            .with_body(&"<p>hello</p>".repeat(100))
+            .with_header("ETag", "\"v1\"")
+    });
+    router.get("/png", |_| {
+        Response::new(200)
+            .with_bytes(vec![0u8; 4096])
+            .with_header("Content-Type", "image/png")
+    });
+    router
+}
+
+#[test]
+fn test_gzip_compression() {
+    let response = router().handle_request(Request::get("/json").with_header("Accept-Encoding", "gzip, deflate"));
+
+    assert_eq!(response.headers.get("Content-Encoding"), Some("gzip"));
+    assert_eq!(response.headers.get("Vary"), Some("Accept-Encoding"));
+
+    let mut decoded = String::new();
+    GzDecoder::new(response.body.as_slice()).read_to_string(&mut decoded).unwrap();
+    assert_eq!(decoded, "{\"key\":\"value\"},".repeat(100));
+
+    let raw = String::from_utf8_lossy(&response.to_bytes()).into_owned();
+    assert!(raw.contains(&format!("Content-Length: {}\r\n", response.body.len())));
+}
+
+#[test]
+fn test_strong_etags_are_weakened_when_compressed() {
+    let router = router();
+
+    let compressed = router.handle_request(Request::get("/tagged").with_header("Accept-Encoding", "gzip"));
+    assert_eq!(compressed.headers.get("Content-Encoding"), Some("gzip"));
+    assert_eq!(compressed.headers.get("ETag"), Some("W/\"v1\""));
+
+    let identity = router.handle_request(Request::get("/tagged"));
+    assert_eq!(identity.headers.get("ETag"), Some("\"v1\""));
+}
+
+#[test]
+fn test_brotli_preferred_on_tie() {
+    let response = router().handle_request(Request::get("/json").with_header("Accept-Encoding", "gzip, br"));
+    assert_eq!(response.headers.get("Content-Encoding"), Some("br"));
+
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(response.body.as_slice(), 4096)
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, "{\"key\":\"value\"},".repeat(100).into_bytes());
+}
+
+#[test]
+fn test_skipped_responses() {
+    let router = router();
+
+    let small = router.handle_request(Request::get("/small").with_header("Accept-Encoding", "gzip"));
+    assert!(!small.headers.contains("Content-Encoding"));
+    assert_eq!(small.body_text(), "tiny");
+
+    let png = router.handle_request(Request::get("/png").with_header("Accept-Encoding", "gzip"));
+    assert!(!png.headers.contains("Content-Encoding"));
+    assert!(!png.headers.contains("Vary"));
+
+    let identity = router.handle_request(Request::get("/json"));
+    assert!(!identity.headers.contains("Content-Encoding"));
+    assert_eq!(identity.headers.get("Vary"), Some("Accept-Encoding"));
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn echo_router(decompression: Decompression) -> Router {
+    let mut router = Router::new();
+    router.use_global(decompression);
+    router.post("/upload", |req: Request| {
+        Response::new(200).with_body(&format!(
+            "{} {} {}",
+            req.header("Content-Encoding").unwrap_or("-"),
+            req.headers.content_length().unwrap_or(0),
+            req.body_text()
+        ))
+    });
+    router
+}
+
+#[test]
+fn test_request_decompression() {
+    let router = echo_router(Decompression::new());
+    let req = Request::post("/upload")
+        .with_bytes(gzip(b"{\"hello\":\"world\"}"))
+        .with_header("Content-Encoding", "gzip");
+
+    let response = router.handle_request(req);
+    assert_eq!(response.body_text(), "- 17 {\"hello\":\"world\"}");
+}
+
+#[test]
+fn test_request_decompression_limits() {
+    let router = echo_router(Decompression::new().max_size(1024));
+
+    let bomb = Request::post("/upload")
+        .with_bytes(gzip(&vec![b'a'; 1024 * 1024]))
+        .with_header("Content-Encoding", "gzip");
+    assert_eq!(router.handle_request(bomb).status_code, 413);
+
+    let unsupported = Request::post("/upload")
+        .with_body("data")
+        .with_header("Content-Encoding", "zstd");
+    let response = router.handle_request(unsupported);
+    assert_eq!(response.status_code, 415);
+    assert_eq!(response.headers.get("Accept-Encoding"), Some("gzip, deflate"));
+
+    let garbage = Request::post("/upload")
+        .with_body("not gzip")
+        .with_header("Content-Encoding", "gzip");
+    assert_eq!(router.handle_request(garbage).status_code, 400);
+}
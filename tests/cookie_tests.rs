@@ -0,0 +1,101 @@
+use chrono::{TimeZone, Utc};
+use rust_http_server::cookie::{Cookie, SameSite};
+use rust_http_server::{Request, Response};
+use std::time::Duration;
+
+#[test]
+fn test_request_cookie_lookup() {
+    let req = Request::get("/")
+        .with_header("Cookie", "theme=dark; session=\"abc123\"")
+        .with_header("Cookie", "lang=en");
+    assert_eq!(req.cookie("theme"), Some("dark"));
+    assert_eq!(req.cookie("session"), Some("abc123"));
+    assert_eq!(req.cookie("lang"), Some("en"));
+    assert_eq!(req.cookie("missing"), None);
+}
+
+#[test]
+fn test_set_cookie_attributes() {
+    let cookie = Cookie::new("id", "a3fWa")
+        .path("/docs")
+        .domain("example.com")
+        .expires(Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap())
+        .max_age(Duration::from_secs(3600))
+        .secure(true)
+        .http_only(true)
+        .same_site(SameSite::Strict);
+    assert_eq!(
+        cookie.to_string(),
+        "id=a3fWa; Path=/docs; Domain=example.com; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=3600; Secure; HttpOnly; SameSite=Strict"
+    );
+
+    assert_eq!(
+        Cookie::new("tracking", "1").same_site(SameSite::None).to_string(),
+        "tracking=1; Secure; SameSite=None"
+    );
+    assert_eq!(
+        Cookie::removal("id").path("/docs").to_string(),
+        "id=; Path=/docs; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0"
+    );
+}
+
+#[test]
+fn test_response_with_cookies() {
+    let response = Response::new(200)
+        .with_cookie(Cookie::new("a", "1"))
+        .with_cookie(Cookie::new("b", "2").http_only(true));
+    let set_cookies: Vec<&str> = response.headers.get_all("Set-Cookie").collect();
+    assert_eq!(set_cookies, vec!["a=1", "b=2; HttpOnly"]);
+
+    assert!(Response::new(200).try_with_cookie(Cookie::new("a", "x; Domain=evil.com")).is_err());
+    assert!(Response::new(200).try_with_cookie(Cookie::new("bad name", "1")).is_err());
+    assert!(Response::new(200).try_with_cookie(Cookie::new("a", "1").path("/\r\nX: y")).is_err());
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+fn test_signed_cookies() {
+    use rust_http_server::cookie::CookieKey;
+
+    let key = CookieKey::from_secret(&[7u8; 32]);
+    let signed = key.sign(Cookie::new("user", "42"));
+    assert!(signed.value().starts_with("42."));
+
+    let req = Request::get("/").with_header("Cookie", &format!("user={}", signed.value()));
+    assert_eq!(req.signed_cookie("user", &key).as_deref(), Some("42"));
+
+    let tampered = signed.value().replacen("42", "43", 1);
+    let req = Request::get("/").with_header("Cookie", &format!("user={}", tampered));
+    assert_eq!(req.signed_cookie("user", &key), None);
+
+    // A value signed for one cookie is not accepted under another name.
+    assert_eq!(key.verify("admin", signed.value()), None);
+    assert_eq!(CookieKey::from_secret(&[8u8; 32]).verify("user", signed.value()), None);
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+fn test_private_cookies() {
+    use rust_http_server::cookie::CookieKey;
+
+    let key = CookieKey::generate();
+    let sealed = key.encrypt(Cookie::new("cart", "sku=1; qty=2").path("/"));
+    assert!(!sealed.value().contains("sku"));
+    assert!(sealed.is_valid());
+
+    let response = Response::new(200).with_cookie(sealed.clone());
+    assert!(response.headers.get("Set-Cookie").unwrap().ends_with("; Path=/"));
+
+    let req = Request::get("/").with_header("Cookie", &format!("cart={}", sealed.value()));
+    assert_eq!(req.private_cookie("cart", &key).as_deref(), Some("sku=1; qty=2"));
+    assert_eq!(key.decrypt("other", sealed.value()), None);
+    assert_eq!(CookieKey::generate().decrypt("cart", sealed.value()), None);
+    assert_eq!(key.decrypt("cart", "AAAA"), None);
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+#[should_panic(expected = "at least 32 bytes")]
+fn test_short_cookie_secret_panics() {
+    rust_http_server::cookie::CookieKey::from_secret(b"too short");
+}
@@ -0,0 +1,102 @@
+use rust_http_server::http_method::HttpMethod;
+use rust_http_server::middleware::Cors;
+use rust_http_server::{Request, Response, Router};
+use std::time::Duration;
+
+fn router_with(cors: Cors) -> Router {
+    let mut router = Router::new();
+    router.use_global(cors);
+    router.get("/api", |_| Response::new(200).with_body("data"));
+    router
+}
+
+fn preflight(origin: &str, method: &str) -> Request {
+    let mut req = Request::get("/api")
+        .with_header("Origin", origin)
+        .with_header("Access-Control-Request-Method", method);
+    req.method = HttpMethod::OPTIONS;
+    req
+}
+
+#[test]
+fn test_preflight_answered_before_routing() {
+    let router = router_with(
+        Cors::new()
+            .allow_origin("https://*.example.com")
+            .allow_methods(&[HttpMethod::GET, HttpMethod::PUT])
+            .allow_headers(&["Content-Type", "X-Api-Key"])
+            .allow_credentials(true)
+            .max_age(Duration::from_secs(600)),
+    );
+
+    let response = router.handle_request(
+        preflight("https://app.example.com", "PUT").with_header("Access-Control-Request-Headers", "x-api-key"),
+    );
+    assert_eq!(response.status_code, 204);
+    assert_eq!(response.headers.get("Access-Control-Allow-Origin"), Some("https://app.example.com"));
+    assert_eq!(response.headers.get("Access-Control-Allow-Methods"), Some("GET, PUT"));
+    assert_eq!(response.headers.get("Access-Control-Allow-Headers"), Some("content-type, x-api-key"));
+    assert_eq!(response.headers.get("Access-Control-Allow-Credentials"), Some("true"));
+    assert_eq!(response.headers.get("Access-Control-Max-Age"), Some("600"));
+
+    assert_eq!(router.handle_request(preflight("https://example.com", "PUT")).status_code, 403);
+    assert_eq!(router.handle_request(preflight("https://evil-example.com", "PUT")).status_code, 403);
+    assert_eq!(router.handle_request(preflight("https://app.example.com", "DELETE")).status_code, 403);
+
+    // Without CORS headers, OPTIONS still goes through routing
+    let mut plain_options = Request::get("/api");
+    plain_options.method = HttpMethod::OPTIONS;
+    assert_eq!(router.handle_request(plain_options).status_code, 405);
+}
+
+#[test]
+fn test_simple_request_headers() {
+    let router = router_with(Cors::new().allow_origin("https://app.example.com").expose_headers(&["X-Total"]));
+
+    let response = router.handle_request(Request::get("/api").with_header("Origin", "https://app.example.com"));
+    assert_eq!(response.body_text(), "data");
+    assert_eq!(response.headers.get("Access-Control-Allow-Origin"), Some("https://app.example.com"));
+    assert_eq!(response.headers.get("Access-Control-Expose-Headers"), Some("X-Total"));
+    assert_eq!(response.headers.get("Vary"), Some("Origin"));
+
+    let response = router.handle_request(Request::get("/api").with_header("Origin", "https://other.com"));
+    assert_eq!(response.body_text(), "data");
+    assert!(!response.headers.contains("Access-Control-Allow-Origin"));
+}
+
+#[test]
+fn test_permissive_uses_wildcard_origin() {
+    let router = router_with(Cors::permissive());
+    let response = router.handle_request(Request::get("/api").with_header("Origin", "http://localhost:3000"));
+    assert_eq!(response.headers.get("Access-Control-Allow-Origin"), Some("*"));
+}
+
+#[test]
+#[should_panic(expected = "Invalid middleware configuration")]
+fn test_invalid_header_name_panics_at_configuration() {
+    Cors::new().expose_headers(&["X-Total Count"]);
+}
+
+#[test]
+fn test_mirrored_headers_must_be_valid_names() {
+    let router = router_with(Cors::new().allow_origin("https://app.example.com").allow_any_header());
+
+    let ok = preflight("https://app.example.com", "GET").with_header("Access-Control-Request-Headers", "x-a, x-b");
+    let response = router.handle_request(ok);
+    assert_eq!(response.headers.get("Access-Control-Allow-Headers"), Some("x-a, x-b"));
+
+    let bad = preflight("https://app.example.com", "GET").with_header("Access-Control-Request-Headers", "x-a, x(b)");
+    assert_eq!(router.handle_request(bad).status_code, 403);
+}
+
+#[test]
+#[should_panic(expected = "allow_credentials can't be combined with allow_any_origin")]
+fn test_any_origin_with_credentials_is_rejected() {
+    Cors::new().allow_any_origin().allow_credentials(true);
+}
+
+#[test]
+#[should_panic(expected = "allow_any_origin can't be combined with allow_credentials")]
+fn test_credentials_with_any_origin_is_rejected() {
+    Cors::new().allow_credentials(true).allow_any_origin();
+}
@@ -0,0 +1,231 @@
+#[cfg(feature = "secure-cookies")]
+use rust_http_server::cookie::CookieKey;
+#[cfg(feature = "secure-cookies")]
+use rust_http_server::middleware::Session;
+use rust_http_server::middleware::{Csrf, CsrfToken, MemoryStore, Sessions};
+use rust_http_server::{Request, Response, Router};
+use std::sync::Arc;
+
+fn app(csrf: Csrf, with_sessions: bool) -> Router {
+    let mut router = Router::new();
+    if with_sessions {
+        router.use_global(Sessions::new(MemoryStore::new()).cookie_name("sid").secure(false));
+    }
+    router.use_global(csrf);
+    router.get("/form", |req: Request| {
+        let token = req.extension::<CsrfToken>().unwrap();
+        Response::new(200).with_body(token.as_str())
+    });
+    router.post("/submit", |_| Response::new(200).with_body("accepted"));
+    router.post("/webhooks/{provider}", |_| Response::new(200).with_body("hook"));
+    router
+}
+
+fn cookie_pair(response: &Response, name: &str) -> String {
+    response
+        .headers
+        .get_all("Set-Cookie")
+        .find(|c| c.starts_with(&format!("{}=", name)))
+        .and_then(|c| c.split(';').next())
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_synchronizer_token() {
+    let router = app(Csrf::synchronizer(), true);
+
+    let response = router.handle_request(Request::get("/form"));
+    let token = response.body_text().into_owned();
+    let sid = cookie_pair(&response, "sid");
+    assert_eq!(token.len(), 43);
+
+    // The token is stable for the session.
+    let response = router.handle_request(Request::get("/form").with_header("Cookie", &sid));
+    assert_eq!(response.body_text(), token);
+
+    let submit = || Request::post("/submit").with_header("Cookie", &sid).with_header("Host", "localhost");
+
+    assert_eq!(router.handle_request(submit()).status_code, 403);
+    assert_eq!(router.handle_request(submit().with_header("X-CSRF-Token", "wrong")).status_code, 403);
+    assert_eq!(router.handle_request(submit().with_header("X-CSRF-Token", &token)).status_code, 200);
+
+    let form = submit()
+        .with_header("Content-Type", "application/x-www-form-urlencoded")
+        .with_body(&format!("title=hello+world&csrf_token={}", token));
+    assert_eq!(router.handle_request(form).status_code, 200);
+
+    // Another session's token is not accepted.
+    let other = router.handle_request(Request::get("/form")).body_text().into_owned();
+    assert_eq!(router.handle_request(submit().with_header("X-CSRF-Token", &other)).status_code, 403);
+}
+
+#[test]
+fn test_synchronizer_issues_tokens_only_when_used() {
+    let store = Arc::new(MemoryStore::new());
+    let mut router = Router::new();
+    router.use_global(Sessions::with_store(store.clone()).cookie_name("sid").secure(false));
+    router.use_global(Csrf::synchronizer());
+    router.get("/", |_| Response::new(200).with_body("home"));
+    router.get("/form", |req: Request| {
+        Response::new(200).with_body(req.extension::<CsrfToken>().unwrap().as_str())
+    });
+
+    // Anonymous traffic that never renders a form leaves nothing behind.
+    for path in ["/", "/missing"] {
+        let response = router.handle_request(Request::get(path));
+        assert!(!response.headers.contains("Set-Cookie"), "{}", path);
+    }
+    let response = router.handle_request(Request::post("/").with_header("X-CSRF-Token", "guess"));
+    assert_eq!(response.status_code, 403);
+    assert!(store.is_empty());
+
+    let response = router.handle_request(Request::get("/form"));
+    assert_eq!(response.body_text().len(), 43);
+    assert!(response.headers.contains("Set-Cookie"));
+    assert_eq!(store.len(), 1);
+}
+
+#[cfg(feature = "secure-cookies")]
+fn double_submit() -> Csrf {
+    Csrf::double_submit(CookieKey::from_secret(&[7; 32])).secure_cookie(false)
+}
+
+// A token and its cookie as issued by `router`.
+#[cfg(feature = "secure-cookies")]
+fn issue(router: &Router, request: Request) -> (String, String) {
+    let response = router.handle_request(request);
+    (response.body_text().into_owned(), cookie_pair(&response, "csrf_token"))
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+fn test_double_submit_cookie() {
+    let router = app(double_submit(), false);
+
+    let response = router.handle_request(Request::get("/form"));
+    let token = response.body_text().into_owned();
+    let cookie = cookie_pair(&response, "csrf_token");
+    assert!(cookie.starts_with(&format!("csrf_token={}.", token)), "{}", cookie);
+    assert!(response.headers.get("Set-Cookie").unwrap().ends_with("; Path=/; SameSite=Strict"));
+
+    let response = router.handle_request(
+        Request::post("/submit").with_header("Cookie", &cookie).with_header("X-CSRF-Token", &token),
+    );
+    assert_eq!(response.status_code, 200);
+
+    // Without the cookie, a token alone proves nothing.
+    let response = router.handle_request(Request::post("/submit").with_header("X-CSRF-Token", &token));
+    assert_eq!(response.status_code, 403);
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+fn test_double_submit_rejects_planted_cookies() {
+    let router = app(double_submit(), false);
+    let submit = |cookie: &str, token: &str| {
+        let request = Request::post("/submit")
+            .with_header("Cookie", cookie)
+            .with_header("X-CSRF-Token", token);
+        router.handle_request(request).status_code
+    };
+
+    // An unsigned cookie, as a sibling subdomain could set it.
+    let token = "t".repeat(43);
+    assert_eq!(submit(&format!("csrf_token={}", token), &token), 403);
+    assert_eq!(submit(&format!("csrf_token={}.forged", token), &token), 403);
+
+    // A cookie signed with another key.
+    let other = app(Csrf::double_submit(CookieKey::generate()).secure_cookie(false), false);
+    let (token, cookie) = issue(&other, Request::get("/form"));
+    assert_eq!(submit(&cookie, &token), 403);
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+fn test_double_submit_is_bound_to_the_session() {
+    let mut router = app(double_submit(), true);
+    router.get("/login", |req: Request| {
+        req.extension::<Session>().unwrap().insert("user", "alice");
+        Response::new(204)
+    });
+
+    let victim = cookie_pair(&router.handle_request(Request::get("/login")), "sid");
+    let attacker = cookie_pair(&router.handle_request(Request::get("/login")), "sid");
+
+    // The attacker's own valid pair doesn't verify in the victim's session.
+    let (token, csrf) = issue(&router, Request::get("/form").with_header("Cookie", &attacker));
+    let submit = |sid: &str| {
+        let request = Request::post("/submit")
+            .with_header("Cookie", &format!("{}; {}", sid, csrf))
+            .with_header("X-CSRF-Token", &token);
+        router.handle_request(request).status_code
+    };
+    assert_eq!(submit(&attacker), 200);
+    assert_eq!(submit(&victim), 403);
+}
+
+#[cfg(feature = "secure-cookies")]
+#[test]
+fn test_origin_and_referer_checks() {
+    let router = app(double_submit().trusted_origin("https://admin.example.com"), false);
+    let (token, cookie) = issue(&router, Request::get("/form"));
+    let submit = || {
+        Request::post("/submit")
+            .with_header("Host", "app.example.com")
+            .with_header("Cookie", &cookie)
+            .with_header("X-CSRF-Token", &token)
+    };
+
+    assert_eq!(router.handle_request(submit()).status_code, 200);
+    let same_origin = submit().with_header("Origin", "http://app.example.com");
+    assert_eq!(router.handle_request(same_origin).status_code, 200);
+    let trusted = submit().with_header("Origin", "https://admin.example.com");
+    assert_eq!(router.handle_request(trusted).status_code, 200);
+
+    let response = router.handle_request(submit().with_header("Origin", "https://evil.example"));
+    assert_eq!(response.status_code, 403);
+    assert_eq!(response.body_text(), "403 Forbidden: cross-origin request rejected");
+    assert_eq!(router.handle_request(submit().with_header("Origin", "null")).status_code, 403);
+
+    let referer = submit().with_header("Referer", "http://app.example.com/form?x=1");
+    assert_eq!(router.handle_request(referer).status_code, 200);
+    let referer = submit().with_header("Referer", "https://evil.example/app.example.com");
+    assert_eq!(router.handle_request(referer).status_code, 403);
+}
+
+#[test]
+fn test_configured_origin_behind_a_proxy() {
+    let router = app(Csrf::synchronizer().origin("https://app.example.com/"), true);
+    let response = router.handle_request(Request::get("/form"));
+    let token = response.body_text().into_owned();
+    let sid = cookie_pair(&response, "sid");
+
+    // TLS ended at the proxy, so the request itself is plain HTTP.
+    let submit = |origin: &str| {
+        let request = Request::post("/submit")
+            .with_header("Host", "app.example.com")
+            .with_header("Cookie", &sid)
+            .with_header("Origin", origin)
+            .with_header("X-CSRF-Token", &token);
+        router.handle_request(request).status_code
+    };
+    assert_eq!(submit("https://app.example.com"), 200);
+    assert_eq!(submit("http://app.example.com"), 403);
+}
+
+#[test]
+fn test_exempt_routes_and_safe_methods() {
+    let router = app(Csrf::synchronizer().exempt("/webhooks/{provider}"), true);
+
+    let response = router.handle_request(Request::post("/webhooks/github").with_header("Origin", "https://github.com"));
+    assert_eq!(response.status_code, 200);
+    assert_eq!(router.handle_request(Request::post("/submit")).status_code, 403);
+    assert_eq!(router.handle_request(Request::get("/form")).status_code, 200);
+}
+
+#[test]
+fn test_synchronizer_without_sessions() {
+    let router = app(Csrf::synchronizer(), false);
+    assert_eq!(router.handle_request(Request::get("/form")).status_code, 500);
+}
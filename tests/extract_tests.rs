@@ -0,0 +1,156 @@
+use rust_http_server::{extract, Extension, HttpMethod, Request, Response, Router, State};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Default)]
+struct App {
+    hits: Arc<AtomicUsize>,
+}
+
+#[derive(Clone)]
+struct User(String);
+
+fn body(response: &Response) -> String {
+    String::from_utf8_lossy(&response.body).into_owned()
+}
+
+#[test]
+fn test_state_and_plain_extractors() {
+    let app = App::default();
+    let mut router = Router::new();
+    router.state(app.clone());
+    router.post(
+        "/echo",
+        extract(|State(app): State<App>, method: HttpMethod, text: String| {
+            app.hits.fetch_add(1, Ordering::SeqCst);
+            format!("{} {}", method, text)
+        }),
+    );
+    router.get("/nothing", extract(|| ()));
+    router.get(
+        "/created",
+        extract(|req: Request| (201, format!("created {}", req.path))),
+    );
+
+    let response = router.handle_request(Request::post("/echo").with_body("hello"));
+    assert_eq!(response.status_code, 200);
+    assert_eq!(body(&response), "POST hello");
+    assert_eq!(app.hits.load(Ordering::SeqCst), 1);
+
+    let response = router.handle_request(Request::post("/echo").with_bytes(vec![0xff, 0xfe]));
+    assert_eq!(response.status_code, 400);
+    assert_eq!(app.hits.load(Ordering::SeqCst), 1);
+
+    assert_eq!(router.handle_request(Request::get("/nothing")).status_code, 204);
+
+    let response = router.handle_request(Request::get("/created"));
+    assert_eq!(response.status_code, 201);
+    assert_eq!(response.reason_phrase, "Created");
+    assert_eq!(body(&response), "created /created");
+}
+
+#[test]
+fn test_missing_state_and_extensions() {
+    let mut router = Router::new();
+    router.get("/state", extract(|State(_): State<App>| "unreachable"));
+    router.get(
+        "/user",
+        extract(|user: Option<Extension<User>>| match user {
+            Some(Extension(User(name))) => name,
+            None => "anonymous".to_string(),
+        }),
+    );
+    router.get("/required", extract(|Extension(User(name)): Extension<User>| name));
+
+    assert_eq!(router.handle_request(Request::get("/state")).status_code, 500);
+    assert_eq!(body(&router.handle_request(Request::get("/user"))), "anonymous");
+    assert_eq!(router.handle_request(Request::get("/required")).status_code, 500);
+
+    let mut request = Request::get("/required");
+    request.extensions.insert(User("ada".to_string()));
+    assert_eq!(body(&router.handle_request(request)), "ada");
+}
+
+#[cfg(feature = "serde")]
+mod typed {
+    use super::*;
+    use rust_http_server::{Form, Json, Path, Query};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize)]
+    struct Membership {
+        team: String,
+        id: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct Filter {
+        active: Option<bool>,
+        #[serde(default)]
+        tag: Vec<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct NewUser {
+        name: String,
+    }
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        router.get("/users/{id}", extract(|Path(id): Path<u64>| format!("user {}", id)));
+        router.get(
+            "/teams/{team}/users/{id}",
+            extract(|Path(m): Path<Membership>, Query(filter): Query<Filter>| {
+                format!("{} {} {:?} {:?}", m.team, m.id, filter.active, filter.tag)
+            }),
+        );
+        router.post(
+            "/users",
+            extract(|Json(user): Json<NewUser>| (201, Json(user))),
+        );
+        router.post("/signup", extract(|Form(user): Form<NewUser>| user.name));
+        router
+    }
+
+    #[test]
+    fn test_path_and_query() {
+        let router = router();
+
+        assert_eq!(body(&router.handle_request(Request::get("/users/42"))), "user 42");
+        let response = router.handle_request(Request::get("/users/abc"));
+        assert_eq!(response.status_code, 400);
+        assert!(body(&response).contains("id"), "{}", body(&response));
+
+        let response = router.handle_request(Request::get("/teams/core/users/7?active=true&tag=a&tag=b"));
+        assert_eq!(body(&response), "core 7 Some(true) [\"a\", \"b\"]");
+        let response = router.handle_request(Request::get("/teams/core/users/7"));
+        assert_eq!(body(&response), "core 7 None []");
+        let response = router.handle_request(Request::get("/teams/core/users/7?active=maybe"));
+        assert_eq!(response.status_code, 422);
+    }
+
+    #[test]
+    fn test_json_and_form_bodies() {
+        let router = router();
+
+        let request = Request::post("/users")
+            .with_header("Content-Type", "application/json")
+            .with_body(r#"{"name":"ada"}"#);
+        let response = router.handle_request(request);
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.headers.get("Content-Type"), Some("application/json"));
+        assert_eq!(body(&response), r#"{"name":"ada"}"#);
+
+        let response = router.handle_request(Request::post("/users").with_body(r#"{"name":"ada"}"#));
+        assert_eq!(response.status_code, 415);
+        let request = Request::post("/users")
+            .with_header("Content-Type", "application/json")
+            .with_body(r#"{"nom":"ada"}"#);
+        assert_eq!(router.handle_request(request).status_code, 422);
+
+        let request = Request::post("/signup")
+            .with_header("Content-Type", "application/x-www-form-urlencoded")
+            .with_body("name=Ada+L");
+        assert_eq!(body(&router.handle_request(request)), "Ada L");
+    }
+}
@@ -0,0 +1,123 @@
+use rust_http_server::{FormData, FormError, Request, Response};
+
+fn form_request(body: &str) -> Request {
+    let mut request = Request::post("/signup").with_header("Content-Type", "application/x-www-form-urlencoded");
+    request.body = body.as_bytes().to_vec();
+    request
+}
+
+#[test]
+fn test_form_decoding() {
+    let form = FormData::parse("name=J%C3%BCrgen+M&tag=a&tag=b&empty=&flag&&note=50%25+off%2B");
+    assert_eq!(form.get("name"), Some("Jürgen M"));
+    assert_eq!(form.get("tag"), Some("a"));
+    assert_eq!(form.get_all("tag"), vec!["a", "b"]);
+    assert_eq!(form.get("empty"), Some(""));
+    assert_eq!(form.get("flag"), Some(""));
+    assert_eq!(form.get("note"), Some("50% off+"));
+    assert_eq!(form.get("missing"), None);
+    assert_eq!(form.len(), 6);
+    assert_eq!(form.to_multimap()["tag"], vec!["a".to_string(), "b".to_string()]);
+}
+
+#[test]
+fn test_request_form_and_query() {
+    let form = form_request("email=ada%40example.com&interest=math&interest=engines").form().unwrap();
+    assert_eq!(form.get("email"), Some("ada@example.com"));
+    assert_eq!(form.get_all("interest"), vec!["math", "engines"]);
+
+    let query = Request::get("/search?q=rust+http&page=2").query();
+    assert_eq!(query.get("q"), Some("rust http"));
+    assert_eq!(query.get("page"), Some("2"));
+    assert!(Request::get("/search").query().is_empty());
+
+    let mut request = Request::post("/signup").with_header("Content-Type", "application/json");
+    request.body = b"email=x".to_vec();
+    let err = request.form().unwrap_err();
+    assert_eq!(err.status(), 415);
+    assert_eq!(Response::from(err).status_code, 415);
+}
+
+#[test]
+fn test_form_field_limit() {
+    let body = vec!["a=1"; 1001].join("&");
+    let err = form_request(&body).form().unwrap_err();
+    assert_eq!(err, FormError::TooManyFields(1000));
+    assert_eq!(Response::from(err).status_code, 413);
+
+    assert!(FormData::parse_limited("a=1&b=2", 2).is_ok());
+    assert!(FormData::parse_limited("a=1&b=2&c=3", 2).is_err());
+}
+
+#[cfg(feature = "serde")]
+mod typed {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Plan {
+        Free,
+        Pro,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Signup {
+        email: String,
+        age: u32,
+        plan: Plan,
+        newsletter: Option<bool>,
+        referrer: Option<String>,
+        #[serde(default)]
+        interest: Vec<String>,
+    }
+
+    #[test]
+    fn test_form_into_struct() {
+        let signup: Signup = form_request("email=ada%40example.com&age=36&plan=pro&newsletter=true&referrer=&interest=math&interest=engines")
+            .form_as()
+            .unwrap();
+        assert_eq!(
+            signup,
+            Signup {
+                email: "ada@example.com".to_string(),
+                age: 36,
+                plan: Plan::Pro,
+                newsletter: Some(true),
+                referrer: None,
+                interest: vec!["math".to_string(), "engines".to_string()],
+            }
+        );
+
+        let signup: Signup = form_request("email=b%40example.com&age=7&plan=free").form_as().unwrap();
+        assert_eq!(signup.plan, Plan::Free);
+        assert_eq!(signup.newsletter, None);
+        assert!(signup.interest.is_empty());
+    }
+
+    #[test]
+    fn test_form_into_struct_errors() {
+        let err = form_request("email=a%40example.com&age=old&plan=pro").form_as::<Signup>().unwrap_err();
+        assert_eq!(err.status(), 422);
+        assert!(err.to_string().starts_with("age: invalid value"), "{}", err);
+
+        let err = form_request("email=a%40example.com&plan=pro").form_as::<Signup>().unwrap_err();
+        assert!(err.to_string().contains("missing field `age`"), "{}", err);
+        let response = Response::from(err);
+        assert_eq!(response.status_code, 422);
+        assert!(response.body_text().starts_with("422 Unprocessable Entity: "));
+    }
+
+    #[test]
+    fn test_query_into_struct() {
+        #[derive(Deserialize)]
+        struct Search {
+            q: String,
+            page: Option<u32>,
+        }
+
+        let search: Search = Request::get("/search?q=rust&page=3").query_as().unwrap();
+        assert_eq!(search.q, "rust");
+        assert_eq!(search.page, Some(3));
+    }
+}
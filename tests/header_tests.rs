@@ -0,0 +1,76 @@
+use rust_http_server::header::InvalidHeader;
+use rust_http_server::{HeaderMap, ParseRequestError, Request, Response};
+
+#[test]
+fn test_header_map_case_insensitive_multi_values() {
+    let mut headers = HeaderMap::new();
+    headers.append("Set-Cookie", "a=1").unwrap();
+    headers.append("X-Other", "x").unwrap();
+    headers.append("set-cookie", "b=2").unwrap();
+
+    assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+    assert_eq!(headers.get_all("Set-Cookie").collect::<Vec<_>>(), vec!["a=1", "b=2"]);
+
+    headers.insert("X-Other", "y").unwrap();
+    assert_eq!(headers.get_all("x-other").collect::<Vec<_>>(), vec!["y"]);
+
+    assert_eq!(headers.remove("Set-Cookie"), vec!["a=1".to_string(), "b=2".to_string()]);
+    assert!(!headers.contains("set-cookie"));
+    assert_eq!(headers.iter().collect::<Vec<_>>(), vec![("X-Other", "y")]);
+}
+
+#[test]
+fn test_header_validation() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(
+        headers.insert("X-Test", "ok\r\nSet-Cookie: evil=1"),
+        Err(InvalidHeader::Value("X-Test".to_string()))
+    );
+    assert_eq!(
+        headers.insert("Bad Name", "ok"),
+        Err(InvalidHeader::Name("Bad Name".to_string()))
+    );
+    assert!(headers.is_empty());
+
+    assert!(Response::new(200).try_with_header("X-Test", "a\nb").is_err());
+}
+
+#[test]
+#[should_panic(expected = "invalid value for header")]
+fn test_response_with_header_rejects_crlf() {
+    let _ = Response::new(200).with_header("Location", "/\r\nSet-Cookie: evil=1");
+}
+
+#[test]
+fn test_typed_accessors() {
+    let req = Request::get("/")
+        .with_header("Content-Length", "42")
+        .with_header("Content-Type", "Application/JSON; charset=utf-8")
+        .with_header("Cookie", "session=abc; theme=\"dark\"")
+        .with_header("Authorization", "Bearer token123");
+
+    assert_eq!(req.headers.content_length(), Some(42));
+    assert_eq!(req.headers.content_type(), Some("Application/JSON; charset=utf-8"));
+    assert_eq!(req.headers.mime_type().as_deref(), Some("application/json"));
+    assert_eq!(req.headers.cookies(), vec![("session", "abc"), ("theme", "dark")]);
+
+    let auth = req.headers.authorization().unwrap();
+    assert_eq!(auth.scheme, "Bearer");
+    assert_eq!(auth.bearer(), Some("token123"));
+}
+
+#[test]
+fn test_request_parsing_rejects_invalid_headers() {
+    for line in ["Bad Header: x", "X-Ok: \x01y", "Host : localhost", " X-Folded: y", "no colon"] {
+        let raw = format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}\r\n\r\n", line);
+        assert!(
+            matches!(Request::from_buffer(raw.as_bytes()), Err(ParseRequestError::Header(_))),
+            "{:?}",
+            line
+        );
+    }
+
+    let req = Request::from_buffer(b"GET / HTTP/1.1\r\nHost:localhost \r\nX-Empty:\r\n\r\n").unwrap();
+    assert_eq!(req.header("host"), Some("localhost"));
+    assert_eq!(req.header("x-empty"), Some(""));
+}
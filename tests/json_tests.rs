@@ -0,0 +1,77 @@
+#![cfg(feature = "serde")]
+
+use rust_http_server::{JsonError, Request, Response, Router};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct NewUser {
+    name: String,
+    age: u32,
+}
+
+fn users_router() -> Router {
+    let mut router = Router::new();
+    router.post("/users", |req: Request| {
+        let user: NewUser = match req.json() {
+            Ok(user) => user,
+            Err(e) => return e.into(),
+        };
+        Response::new(201).with_json(&user)
+    });
+    router
+}
+
+fn post_users(content_type: Option<&str>, body: &str) -> Response {
+    let mut request = Request::post("/users");
+    if let Some(content_type) = content_type {
+        request = request.with_header("Content-Type", content_type);
+    }
+    request.body = body.as_bytes().to_vec();
+    users_router().handle_request(request)
+}
+
+#[test]
+fn test_json_round_trip() {
+    let response = post_users(Some("application/json; charset=utf-8"), r#"{"name":"Ada","age":36}"#);
+    assert_eq!(response.status_code, 201);
+    assert_eq!(response.headers.get("Content-Type"), Some("application/json"));
+    assert_eq!(response.body_text(), r#"{"name":"Ada","age":36}"#);
+
+    let response = Response::json(&[1, 2, 3]);
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body_text(), "[1,2,3]");
+}
+
+#[test]
+fn test_json_content_type_is_checked() {
+    let response = post_users(None, r#"{"name":"Ada","age":36}"#);
+    assert_eq!(response.status_code, 415);
+
+    let response = post_users(Some("text/plain"), r#"{"name":"Ada","age":36}"#);
+    assert_eq!(response.status_code, 415);
+    assert!(response.body_text().contains("got text/plain"), "{}", response.body_text());
+
+    let response = post_users(Some("application/merge-patch+json"), r#"{"name":"Ada","age":36}"#);
+    assert_eq!(response.status_code, 201);
+}
+
+#[test]
+fn test_json_errors_report_location() {
+    let response = post_users(Some("application/json"), "{\n  \"name\": \"Ada\",\n  \"age\": }");
+    assert_eq!(response.status_code, 400);
+    let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["line"], 3);
+    assert_eq!(body["column"], 10);
+
+    let response = post_users(Some("application/json"), r#"{"name":"Ada","age":"old"}"#);
+    assert_eq!(response.status_code, 422);
+    assert_eq!(response.headers.get("Content-Type"), Some("application/json"));
+    let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("invalid type"), "{}", body);
+    assert_eq!(body["line"], 1);
+
+    let request = Request::post("/users").with_header("Content-Type", "application/json");
+    let err = request.json::<NewUser>().unwrap_err();
+    assert!(matches!(err, JsonError::Syntax(_)));
+    assert_eq!(err.status(), 400);
+}
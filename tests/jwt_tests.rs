@@ -0,0 +1,154 @@
+#![cfg(feature = "jwt")]
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use rust_http_server::middleware::{Claims, JwtAuth, Principal};
+use rust_http_server::{Request, Response, Router};
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const RSA_PRIVATE: &[u8] = include_bytes!("fixtures/jwt/rsa_private.pem");
+const RSA_PUBLIC: &[u8] = include_bytes!("fixtures/jwt/rsa_public.pem");
+const ED25519_PRIVATE: &[u8] = include_bytes!("fixtures/jwt/ed25519_private.pem");
+const ED25519_PUBLIC: &[u8] = include_bytes!("fixtures/jwt/ed25519_public.pem");
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+fn claims(extra: Value) -> Value {
+    let mut claims = json!({ "sub": "user-1", "exp": now() + 300, "iss": "gateway", "aud": "orders" });
+    claims.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    claims
+}
+
+fn hs256(claims: &Value) -> String {
+    encode(&Header::new(Algorithm::HS256), claims, &EncodingKey::from_secret(b"shared-secret")).unwrap()
+}
+
+fn router_with(auth: JwtAuth) -> Router {
+    let mut router = Router::new();
+    router.use_global(auth);
+    router.get("/me", |req: Request| {
+        let principal = req.extension::<Principal>().unwrap();
+        let tenant = req.extension::<Claims>().and_then(|c| c.get("tenant")).and_then(Value::as_str);
+        Response::new(200).with_body(&format!("{} {}", principal.name, tenant.unwrap_or("-")))
+    });
+    router
+}
+
+fn bearer(router: &Router, token: &str) -> Response {
+    router.handle_request(Request::get("/me").with_header("Authorization", &format!("Bearer {}", token)))
+}
+
+fn challenge(response: &Response) -> &str {
+    response.headers.get("WWW-Authenticate").unwrap_or("")
+}
+
+#[test]
+fn test_hs256_claims_are_checked() {
+    let router = router_with(JwtAuth::new().hs256(b"shared-secret").issuer("gateway").audience("orders").realm("api"));
+
+    let response = router.handle_request(Request::get("/me"));
+    assert_eq!(response.status_code, 401);
+    assert_eq!(challenge(&response), "Bearer realm=\"api\"");
+
+    let response = bearer(&router, &hs256(&claims(json!({ "tenant": "acme" }))));
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body_text(), "user-1 acme");
+
+    let expired = bearer(&router, &hs256(&claims(json!({ "exp": now() - 3600 }))));
+    assert_eq!(expired.status_code, 401);
+    assert_eq!(
+        challenge(&expired),
+        "Bearer realm=\"api\", error=\"invalid_token\", error_description=\"token expired\""
+    );
+
+    let cases = [
+        (claims(json!({ "iss": "elsewhere" })), "invalid issuer"),
+        (claims(json!({ "aud": "billing" })), "invalid audience"),
+        (claims(json!({ "nbf": now() + 3600 })), "token not yet valid"),
+    ];
+    for (claims, description) in cases {
+        let response = bearer(&router, &hs256(&claims));
+        assert_eq!(response.status_code, 401);
+        assert!(challenge(&response).contains(description), "{}", challenge(&response));
+    }
+
+    // A configured issuer or audience makes the claim mandatory.
+    for claim in ["iss", "aud"] {
+        let mut claims = claims(json!({}));
+        claims.as_object_mut().unwrap().remove(claim);
+        let response = bearer(&router, &hs256(&claims));
+        assert_eq!(response.status_code, 401, "token without {}", claim);
+        assert!(challenge(&response).contains("missing required claim"), "{}", challenge(&response));
+    }
+
+    let forged = encode(&Header::new(Algorithm::HS256), &claims(json!({})), &EncodingKey::from_secret(b"guess")).unwrap();
+    assert!(challenge(&bearer(&router, &forged)).contains("invalid signature"));
+    assert!(challenge(&bearer(&router, "not.a.jwt")).contains("malformed token"));
+}
+
+#[test]
+fn test_clock_skew_leeway() {
+    let token = hs256(&claims(json!({ "exp": now() - 30 })));
+
+    let strict = router_with(JwtAuth::new().hs256(b"shared-secret").leeway(Duration::ZERO));
+    assert_eq!(bearer(&strict, &token).status_code, 401);
+
+    let lenient = router_with(JwtAuth::new().hs256(b"shared-secret"));
+    assert_eq!(bearer(&lenient, &token).status_code, 200);
+}
+
+#[test]
+fn test_rs256_and_eddsa_pem_keys() {
+    let router = router_with(
+        JwtAuth::new()
+            .rs256_pem(RSA_PUBLIC)
+            .unwrap()
+            .ed25519_pem(ED25519_PUBLIC)
+            .unwrap(),
+    );
+
+    let rs256 = encode(&Header::new(Algorithm::RS256), &claims(json!({})), &EncodingKey::from_rsa_pem(RSA_PRIVATE).unwrap()).unwrap();
+    assert_eq!(bearer(&router, &rs256).status_code, 200);
+
+    let eddsa = encode(&Header::new(Algorithm::EdDSA), &claims(json!({})), &EncodingKey::from_ed_pem(ED25519_PRIVATE).unwrap()).unwrap();
+    assert_eq!(bearer(&router, &eddsa).status_code, 200);
+
+    // HS256 signed with the public key must not be accepted by an RS256-only setup.
+    let confused = encode(&Header::new(Algorithm::HS256), &claims(json!({})), &EncodingKey::from_secret(RSA_PUBLIC)).unwrap();
+    assert!(challenge(&bearer(&router, &confused)).contains("unknown signing key"));
+}
+
+#[test]
+fn test_jwks_file_selects_key_by_kid() {
+    let router = router_with(JwtAuth::new().jwks_file("tests/fixtures/jwt/jwks.json").unwrap());
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("rsa-2026".to_string());
+    let token = encode(&header, &claims(json!({})), &EncodingKey::from_rsa_pem(RSA_PRIVATE).unwrap()).unwrap();
+    assert_eq!(bearer(&router, &token).status_code, 200);
+
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some("ed-2026".to_string());
+    let token = encode(&header, &claims(json!({})), &EncodingKey::from_ed_pem(ED25519_PRIVATE).unwrap()).unwrap();
+    assert_eq!(bearer(&router, &token).status_code, 200);
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("retired".to_string());
+    let token = encode(&header, &claims(json!({})), &EncodingKey::from_rsa_pem(RSA_PRIVATE).unwrap()).unwrap();
+    assert!(challenge(&bearer(&router, &token)).contains("unknown signing key"));
+}
+
+#[test]
+fn test_token_from_cookie() {
+    let router = router_with(JwtAuth::new().hs256(b"shared-secret").cookie("access_token"));
+    let token = hs256(&claims(json!({})));
+
+    let response = router.handle_request(
+        Request::get("/me").with_header("Cookie", &format!("theme=dark; access_token={}", token)),
+    );
+    assert_eq!(response.status_code, 200);
+
+    assert!(JwtAuth::new().jwks("{").is_err());
+}
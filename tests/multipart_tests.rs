@@ -0,0 +1,201 @@
+use std::io::Read;
+
+use rust_http_server::{Multipart, MultipartError, Request, Response};
+
+const BOUNDARY: &str = "----WebKitFormBoundary7MA4YWxkTrZu0gW";
+
+fn body(parts: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+    let mut body = b"preamble to ignore\r\n".to_vec();
+    for (name, filename, content) in parts {
+        body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+        match filename {
+            Some(filename) => body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                    name, filename
+                )
+                .as_bytes(),
+            ),
+            None => body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes()),
+        }
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\nepilogue", BOUNDARY).as_bytes());
+    body
+}
+
+fn upload_request(body: Vec<u8>) -> Request {
+    let mut request = Request::post("/upload")
+        .with_header("Content-Type", &format!("multipart/form-data; boundary={}", BOUNDARY));
+    request.body = body;
+    request
+}
+
+// Hands out the input a few bytes at a time, as a socket might.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(3);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_multipart_fields_and_files() {
+    let request = upload_request(body(&[
+        ("title", None, b"Holiday \xc3\xa9t\xc3\xa9"),
+        ("skipped", None, b"never read"),
+        ("photo", Some("beach.png"), b"\x89PNG\r\n\x1a\n\0binary"),
+        ("empty", None, b""),
+    ]));
+    let mut multipart = request.multipart().unwrap();
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.name(), "title");
+    assert_eq!(part.filename(), None);
+    assert!(!part.is_file());
+    assert_eq!(part.read_text().unwrap(), "Holiday été");
+
+    let part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.name(), "skipped");
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.name(), "photo");
+    assert_eq!(part.filename(), Some("beach.png"));
+    assert_eq!(part.content_type(), Some("application/octet-stream"));
+    assert_eq!(part.headers().len(), 2);
+    assert_eq!(part.read_bytes().unwrap(), b"\x89PNG\r\n\x1a\n\0binary");
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.name(), "empty");
+    assert_eq!(part.read_text().unwrap(), "");
+
+    assert!(multipart.next_part().unwrap().is_none());
+    assert!(multipart.next_part().unwrap().is_none());
+}
+
+#[test]
+fn test_multipart_streams_across_small_reads() {
+    // Content with near-misses of the delimiter, split over many tiny reads.
+    let content = format!("line one\r\n--{}x\r\n--\r\n-", &BOUNDARY[..20]).repeat(200);
+    let body = body(&[("log", Some("app.log"), content.as_bytes()), ("note", None, b"done")]);
+    let mut multipart = Multipart::new(Trickle(&body), BOUNDARY);
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    let mut read = String::new();
+    part.read_to_string(&mut read).unwrap();
+    assert_eq!(read, content);
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.read_text().unwrap(), "done");
+    assert!(multipart.next_part().unwrap().is_none());
+}
+
+#[test]
+fn test_multipart_saves_files() {
+    let dir = std::env::temp_dir().join(format!("multipart-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let content = vec![7u8; 100_000];
+    let request = upload_request(body(&[("a", Some("a.bin"), &content), ("b", Some("b.bin"), &content), ("c", Some("c.bin"), b"gone")]));
+    let mut multipart = request.multipart().unwrap().temp_dir(&dir);
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    let written = part.save_to(dir.join("a.bin")).unwrap();
+    assert_eq!(written, 100_000);
+    assert_eq!(std::fs::read(dir.join("a.bin")).unwrap(), content);
+
+    let mut part = multipart.next_part().unwrap().unwrap();
+    let temp = part.save_temp().unwrap();
+    assert_eq!(temp.size(), 100_000);
+    assert!(temp.path().starts_with(&dir));
+    temp.persist(dir.join("b.bin")).unwrap();
+    assert_eq!(std::fs::read(dir.join("b.bin")).unwrap(), content);
+
+    // Dropped without being persisted.
+    let mut part = multipart.next_part().unwrap().unwrap();
+    let temp = part.save_temp().unwrap();
+    let path = temp.path().to_path_buf();
+    assert!(path.exists());
+    drop(temp);
+    assert!(!path.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multipart_limits() {
+    let content = vec![b'x'; 2000];
+    let body = body(&[("a", None, b"small"), ("b", Some("big.txt"), &content)]);
+
+    let mut multipart = Multipart::new(&body[..], BOUNDARY).max_part_size(Some(1000));
+    multipart.next_part().unwrap().unwrap().read_text().unwrap();
+    let err = multipart.next_part().unwrap().unwrap().read_bytes().unwrap_err();
+    assert!(matches!(err, MultipartError::PartTooLarge(1000)), "{}", err);
+    assert_eq!(Response::from(err).status_code, 413);
+
+    let mut multipart = Multipart::new(&body[..], BOUNDARY).max_total_size(Some(1000));
+    let err = loop {
+        match multipart.next_part() {
+            Ok(Some(mut part)) => {
+                if let Err(e) = part.read_bytes() {
+                    break e;
+                }
+            }
+            Ok(None) => panic!("limit not enforced"),
+            Err(e) => break e,
+        }
+    };
+    assert!(matches!(err, MultipartError::TooLarge(1000)), "{}", err);
+
+    let mut multipart = Multipart::new(&body[..], BOUNDARY).max_parts(Some(1));
+    assert!(multipart.next_part().unwrap().is_some());
+    let err = multipart.next_part().err().unwrap();
+    assert!(matches!(err, MultipartError::TooManyParts(1)), "{}", err);
+}
+
+#[test]
+fn test_multipart_rejects_bad_input() {
+    let err = Request::post("/upload").multipart().err().unwrap();
+    assert_eq!(err.status(), 415);
+
+    let request = Request::post("/upload").with_header("Content-Type", "multipart/form-data");
+    let err = request.multipart().err().unwrap();
+    assert_eq!(Response::from(err).body_text(), "400 Bad Request: malformed multipart body: missing boundary");
+
+    let request = Request::post("/upload").with_header("Content-Type", "multipart/form-data; boundary=\"quoted:boundary\"");
+    assert!(request.multipart().is_ok());
+
+    // Cut off in the middle of a part.
+    let mut body = body(&[("a", None, b"hello world")]);
+    body.truncate(body.len() - 50);
+    let mut multipart = Multipart::new(&body[..], BOUNDARY);
+    let err = multipart.next_part().unwrap().unwrap().read_bytes().unwrap_err();
+    assert_eq!(err.status(), 400);
+
+    let body = format!("--{}\r\nContent-Type: text/plain\r\n\r\nx\r\n--{}--", BOUNDARY, BOUNDARY);
+    let mut multipart = Multipart::new(body.as_bytes(), BOUNDARY);
+    let err = multipart.next_part().err().unwrap();
+    assert!(err.to_string().contains("without Content-Disposition"), "{}", err);
+
+    for header in ["Content-Disposition : form-data; name=\"a\"", "X-Note: \x01", "no colon"] {
+        let body = format!("--{}\r\n{}\r\n\r\nx\r\n--{}--", BOUNDARY, header, BOUNDARY);
+        let mut multipart = Multipart::new(body.as_bytes(), BOUNDARY);
+        let err = multipart.next_part().err().unwrap();
+        assert_eq!(err.to_string(), "malformed multipart body: invalid part header", "{:?}", header);
+    }
+}
+
+#[test]
+fn test_multipart_extended_filename() {
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"doc\"; filename=\"naive.txt\"; filename*=UTF-8''na%C3%AFve.txt\r\n\r\nx\r\n--{b}--",
+        b = BOUNDARY
+    );
+    let mut multipart = Multipart::new(body.as_bytes(), BOUNDARY);
+    let part = multipart.next_part().unwrap().unwrap();
+    assert_eq!(part.filename(), Some("naïve.txt"));
+}
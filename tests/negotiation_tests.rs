@@ -0,0 +1,75 @@
+use rust_http_server::negotiation::parse_quality_list;
+use rust_http_server::{Request, Response, Router};
+
+#[test]
+fn test_parse_quality_list() {
+    let items = parse_quality_list("text/html;level=1, application/json;q=0.9, */*;q=0.1, text/plain");
+    let values: Vec<_> = items.iter().map(|i| (i.value.as_str(), i.quality)).collect();
+    assert_eq!(
+        values,
+        vec![
+            ("text/html", 1.0),
+            ("text/plain", 1.0),
+            ("application/json", 0.9),
+            ("*/*", 0.1),
+        ]
+    );
+}
+
+#[test]
+fn test_negotiate_media_type() {
+    let req = Request::get("/").with_header("Accept", "text/html;q=0.5, application/*;q=0.8");
+    assert_eq!(req.negotiate(&["text/html", "application/json"]), Some("application/json"));
+
+    let req = Request::get("/").with_header("Accept", "application/json;q=0, */*");
+    assert_eq!(req.negotiate(&["application/json", "text/html"]), Some("text/html"));
+
+    let req = Request::get("/").with_header("Accept", "image/png");
+    assert_eq!(req.negotiate(&["application/json", "text/html"]), None);
+
+    let req = Request::get("/");
+    assert_eq!(req.negotiate(&["application/json", "text/html"]), Some("application/json"));
+}
+
+#[test]
+fn test_negotiate_language_encoding_charset() {
+    let req = Request::get("/")
+        .with_header("Accept-Language", "de-CH, de;q=0.9, en;q=0.5")
+        .with_header("Accept-Encoding", "gzip;q=0.5, br")
+        .with_header("Accept-Charset", "iso-8859-1;q=0.2, utf-8");
+
+    assert_eq!(req.negotiate_language(&["en-US", "de-DE"]), Some("de-DE"));
+    assert_eq!(req.negotiate_language(&["fr"]), None);
+    assert_eq!(req.negotiate_encoding(&["gzip", "br", "identity"]), Some("br"));
+    assert_eq!(req.negotiate_encoding(&["deflate", "identity"]), Some("identity"));
+    assert_eq!(req.negotiate_charset(&["iso-8859-1", "utf-8"]), Some("utf-8"));
+
+    let req = Request::get("/").with_header("Accept-Encoding", "gzip, identity;q=0");
+    assert_eq!(req.negotiate_encoding(&["identity"]), None);
+}
+
+#[test]
+fn test_router_media_type_routes() {
+    let mut router = Router::new();
+    router.get_producing("/report", "application/json", |_| {
+        Response::new(200).with_body("{}")
+    });
+    router.get_producing("/report", "text/html", |_| {
+        Response::new(200).with_body("<p>report</p>")
+    });
+
+    let response = router.handle_request(Request::get("/report").with_header("Accept", "text/html"));
+    assert_eq!(response.body_text(), "<p>report</p>");
+    assert_eq!(response.headers.get("Content-Type"), Some("text/html"));
+    assert_eq!(response.headers.get("Vary"), Some("Accept"));
+
+    let response = router.handle_request(Request::get("/report").with_header("Accept", "application/*"));
+    assert_eq!(response.body_text(), "{}");
+
+    let response = router.handle_request(Request::get("/report").with_header("Accept", "image/png"));
+    assert_eq!(response.status_code, 406);
+
+    router.get("/report", |_| Response::new(200).with_body("plain"));
+    let response = router.handle_request(Request::get("/report").with_header("Accept", "image/png"));
+    assert_eq!(response.body_text(), "plain");
+}
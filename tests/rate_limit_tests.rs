@@ -0,0 +1,68 @@
+use rust_http_server::middleware::RateLimit;
+use rust_http_server::{Request, Response, Router};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+fn router_with(limit: RateLimit) -> Router {
+    let mut router = Router::new();
+    router.use_global(limit);
+    router.get("/", |_| Response::new(200).with_body("ok"));
+    router
+}
+
+fn from_ip(ip: &str) -> Request {
+    let addr: SocketAddr = format!("{}:5000", ip).parse().unwrap();
+    Request::get("/").with_remote_addr(addr)
+}
+
+#[test]
+fn test_token_bucket_per_ip() {
+    let router = router_with(RateLimit::token_bucket(2, Duration::from_secs(60)));
+
+    let first = router.handle_request(from_ip("10.0.0.1"));
+    assert_eq!(first.status_code, 200);
+    assert_eq!(first.headers.get("RateLimit-Limit"), Some("2"));
+    assert_eq!(first.headers.get("RateLimit-Remaining"), Some("1"));
+
+    assert_eq!(router.handle_request(from_ip("10.0.0.1")).status_code, 200);
+
+    let limited = router.handle_request(from_ip("10.0.0.1"));
+    assert_eq!(limited.status_code, 429);
+    assert_eq!(limited.headers.get("Retry-After"), Some("30"));
+    assert_eq!(limited.headers.get("RateLimit-Remaining"), Some("0"));
+
+    assert_eq!(router.handle_request(from_ip("10.0.0.2")).status_code, 200);
+}
+
+#[test]
+fn test_sliding_window_by_header() {
+    let router = router_with(RateLimit::sliding_window(1, Duration::from_secs(60)).key_by_header("X-Api-Key"));
+
+    let with_key = |key: &str| router.handle_request(from_ip("10.0.0.1").with_header("X-Api-Key", key));
+    assert_eq!(with_key("a").status_code, 200);
+    assert_eq!(with_key("a").status_code, 429);
+    assert_eq!(with_key("b").status_code, 200);
+}
+
+#[test]
+fn test_token_bucket_refills() {
+    let router = router_with(RateLimit::token_bucket(1, Duration::from_millis(50)));
+
+    assert_eq!(router.handle_request(from_ip("10.0.0.1")).status_code, 200);
+    assert_eq!(router.handle_request(from_ip("10.0.0.1")).status_code, 429);
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(router.handle_request(from_ip("10.0.0.1")).status_code, 200);
+}
+
+#[test]
+fn test_custom_key_can_exempt_requests() {
+    let router = router_with(
+        RateLimit::token_bucket(1, Duration::from_secs(60))
+            .key_by(|req: &Request| req.header("X-User").map(str::to_string)),
+    );
+
+    assert_eq!(router.handle_request(Request::get("/")).status_code, 200);
+    assert_eq!(router.handle_request(Request::get("/")).status_code, 200);
+    assert_eq!(router.handle_request(Request::get("/").with_header("X-User", "u")).status_code, 200);
+    assert_eq!(router.handle_request(Request::get("/").with_header("X-User", "u")).status_code, 429);
+}
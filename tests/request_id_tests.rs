@@ -0,0 +1,111 @@
+use rust_http_server::middleware::{RequestId, RequestIds};
+use rust_http_server::path_pattern::is_uuid;
+use rust_http_server::{Logger, Request, Response, Router};
+
+fn router_with(ids: RequestIds) -> Router {
+    let mut router = Router::new();
+    router.use_global(ids);
+    router.get("/", |req: Request| Response::new(200).with_body(req.request_id().unwrap_or("none")));
+    router.get("/fail", |_| Response::new(500));
+    router
+}
+
+#[test]
+fn test_generates_uuid_by_default() {
+    let router = router_with(RequestIds::default());
+
+    let response = router.handle_request(Request::get("/"));
+    let id = response.headers.get("X-Request-Id").unwrap().to_string();
+    assert!(is_uuid(&id), "{}", id);
+    assert_eq!(&id[14..15], "4");
+    assert_eq!(response.body_text(), id);
+    assert_eq!(response.extensions.get::<RequestId>().map(RequestId::as_str), Some(id.as_str()));
+
+    let other = router.handle_request(Request::get("/"));
+    assert_ne!(other.headers.get("X-Request-Id"), Some(id.as_str()));
+}
+
+#[test]
+fn test_ulid_format_and_ordering() {
+    let router = router_with(RequestIds::ulid());
+
+    let first = router.handle_request(Request::get("/")).body_text().into_owned();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let second = router.handle_request(Request::get("/")).body_text().into_owned();
+    assert_eq!(first.len(), 26);
+    assert!(first.bytes().all(|b| b"0123456789ABCDEFGHJKMNPQRSTVWXYZ".contains(&b)), "{}", first);
+    assert!(first < second);
+}
+
+#[test]
+fn test_incoming_id_propagation() {
+    let router = router_with(RequestIds::uuid().header("X-Correlation-Id"));
+
+    let response = router.handle_request(Request::get("/").with_header("X-Correlation-Id", "edge-1234"));
+    assert_eq!(response.body_text(), "edge-1234");
+    assert_eq!(response.headers.get("X-Correlation-Id"), Some("edge-1234"));
+
+    // Values that could forge log entries are replaced.
+    let response = router.handle_request(Request::get("/").with_header("X-Correlation-Id", "a b\" 200"));
+    assert!(is_uuid(&response.body_text()));
+
+    let router = router_with(RequestIds::uuid().trust_incoming(false));
+    let response = router.handle_request(Request::get("/").with_header("X-Request-Id", "edge-1234"));
+    assert_ne!(response.body_text(), "edge-1234");
+}
+
+#[tokio::test]
+async fn test_logger_includes_request_id() {
+    let dir = std::env::temp_dir().join(format!("request-id-logs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let access_path = dir.join("access.log");
+    let error_path = dir.join("error.log");
+    let logger = Logger::new(access_path.to_str().unwrap())
+        .await
+        .unwrap()
+        .with_error_log(error_path.to_str().unwrap())
+        .await
+        .unwrap();
+
+    let router = router_with(RequestIds::uuid());
+    let request = Request::get("/fail").with_header("X-Request-Id", "req-42");
+    let response = router.handle_request(request.clone());
+    logger.access(&request, &response).await;
+    logger.error(Some("req-42"), "handler blew up").await;
+
+    let access = std::fs::read_to_string(&access_path).unwrap();
+    assert!(access.trim_end().ends_with("\"GET /fail HTTP/1.1\" 500 0 request_id=req-42"), "{}", access);
+    let errors = std::fs::read_to_string(&error_path).unwrap();
+    assert!(errors.contains("[error] request_id=req-42 handler blew up"), "{}", errors);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_handlers_log_with_the_request_id() {
+    let dir = std::env::temp_dir().join(format!("request-logger-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let error_path = dir.join("error.log");
+    let logger = Logger::new(dir.join("access.log").to_str().unwrap())
+        .await
+        .unwrap()
+        .with_error_log(error_path.to_str().unwrap())
+        .await
+        .unwrap();
+
+    let mut router = Router::new();
+    router.use_global(RequestIds::uuid());
+    router.get("/charge", |req: Request| {
+        req.logger().error("payment provider timed out");
+        Response::new(502)
+    });
+    let mut request = Request::get("/charge").with_header("X-Request-Id", "req-7");
+    request.extensions.insert(logger);
+    tokio::task::spawn_blocking(move || router.handle_request(request)).await.unwrap();
+
+    // The line is written in the background.
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let errors = std::fs::read_to_string(&error_path).unwrap();
+    assert!(errors.contains("[error] request_id=req-7 payment provider timed out"), "{}", errors);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,225 @@
+use rust_http_server::{HttpMethod, Request, Response, Router};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn ok(_req: Request) -> Response {
+    Response::new(200).with_body("ok")
+}
+
+#[test]
+fn test_routes_listing() {
+    let mut router = Router::new();
+    router.get("/users", ok).name("list_users");
+    router.post("/users", ok);
+    router.get_with_middlewares(
+        "/admin",
+        ok,
+        vec![Arc::new(|req: Request, next: rust_http_server::NextFn| next(req))],
+    );
+
+    let routes: Vec<_> = router
+        .routes()
+        .map(|r| (r.method.clone(), r.pattern.to_string(), r.name.map(String::from), r.middleware_count))
+        .collect();
+
+    assert_eq!(
+        routes,
+        vec![
+            (HttpMethod::GET, "/admin".to_string(), None, 1),
+            (HttpMethod::GET, "/users".to_string(), Some("list_users".to_string()), 0),
+            (HttpMethod::POST, "/users".to_string(), None, 0),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Route conflict: GET /users is already registered")]
+fn test_duplicate_route_panics() {
+    let mut router = Router::new();
+    router.get("/users", ok);
+    router.get("/users", ok);
+}
+
+#[test]
+#[should_panic(expected = "a route named 'users' is already registered")]
+fn test_duplicate_route_name_panics() {
+    let mut router = Router::new();
+    router.get("/users", ok).name("users");
+    router.post("/users", ok).name("users");
+}
+
+#[test]
+fn test_route_listing_endpoint() {
+    let mut router = Router::new();
+    router.get("/users", ok).name("list_users");
+    router.enable_route_listing("/_routes");
+
+    let text = router.handle_request(Request::get("/_routes"));
+    assert_eq!(text.status_code, 200);
+    assert!(text.body_text().contains("/users"));
+    assert!(text.body_text().contains("list_users"));
+
+    let json = router.handle_request(Request::get("/_routes?format=json"));
+    assert_eq!(json.headers.get("Content-Type"), Some("application/json"));
+    assert!(json.body_text().contains(
+        r#"{"host":null,"method":"GET","pattern":"/users","produces":null,"name":"list_users","middleware_count":0}"#
+    ));
+
+    let post = router.handle_request(Request::post("/_routes"));
+    assert_eq!(post.status_code, 405);
+}
+
+#[test]
+#[should_panic(expected = "Route conflict: /_routes is already registered and can't be the route listing")]
+fn test_route_listing_on_a_registered_path_panics() {
+    let mut router = Router::new();
+    router.post("/_routes", ok);
+    router.enable_route_listing("/_routes");
+}
+
+#[test]
+#[should_panic(expected = "Route conflict: GET /_routes is the route listing")]
+fn test_route_on_the_listing_path_panics() {
+    let mut router = Router::new();
+    router.enable_route_listing("/_routes");
+    router.get("/{page}", ok);
+    router.get("/_routes", ok);
+}
+
+#[test]
+fn test_host_routing() {
+    let mut api = Router::new();
+    api.get("/", |_| Response::new(200).with_body("api"));
+
+    let mut tenants = Router::new();
+    tenants.get("/", |req: Request| {
+        Response::new(200).with_body(&format!("tenant {}", req.param("tenant").unwrap_or("?")))
+    });
+
+    let mut wildcard = Router::new();
+    wildcard.get("/", |_| Response::new(200).with_body("wildcard"));
+
+    let mut router = Router::new();
+    router.get("/", |_| Response::new(200).with_body("default"));
+    router.host("*.example.com", wildcard);
+    router.host("{tenant}.tenants.example.com", tenants);
+    router.host("api.example.com", api);
+
+    let body_for = |host: &str| {
+        router
+            .handle_request(Request::get("/").with_header("Host", host))
+            .body_text()
+            .into_owned()
+    };
+
+    assert_eq!(body_for("api.example.com:8080"), "api");
+    assert_eq!(body_for("API.Example.com"), "api");
+    assert_eq!(body_for("www.example.com"), "wildcard");
+    assert_eq!(body_for("a.b.example.com"), "wildcard");
+    assert_eq!(body_for("acme.tenants.example.com"), "tenant acme");
+    assert_eq!(body_for("example.com"), "default");
+    assert_eq!(body_for("localhost"), "default");
+    assert_eq!(router.handle_request(Request::get("/")).body_text(), "default");
+
+    let routes: Vec<_> = router.routes().map(|r| r.host).collect();
+    assert_eq!(
+        routes,
+        vec![None, Some("*.example.com"), Some("api.example.com"), Some("{tenant}.tenants.example.com")]
+    );
+}
+
+#[test]
+fn test_host_captures_beat_wildcards() {
+    let mut tenants = Router::new();
+    tenants.get("/", |req: Request| {
+        Response::new(200).with_body(&format!("tenant {}", req.param("tenant").unwrap_or("?")))
+    });
+
+    let mut router = Router::new();
+    router.host("*.example.com", Router::new());
+    router.host("{tenant}.example.com", tenants);
+
+    let response = router.handle_request(Request::get("/").with_header("Host", "acme.example.com"));
+    assert_eq!(response.body_text(), "tenant acme");
+}
+
+#[test]
+fn test_path_constraints() {
+    let mut router = Router::new();
+    router.get("/users/{id:int}", |req: Request| {
+        let id = match req.param_u64("id") {
+            Ok(id) => id,
+            Err(e) => return e.into(),
+        };
+        Response::new(200).with_body(&format!("user {}", id))
+    });
+    router.get("/users/{name:slug}", |req: Request| {
+        Response::new(200).with_body(&format!("slug {}", req.param("name").unwrap()))
+    });
+    router.get("/users/me", |_| Response::new(200).with_body("me"));
+    router.get("/items/{id:uuid}", |req: Request| match req.param_uuid("id") {
+        Ok(id) => Response::new(200).with_body(&id),
+        Err(e) => e.into(),
+    });
+    router.get("/codes/{code:[A-Z]{3}}", |req: Request| {
+        Response::new(200).with_body(req.param("code").unwrap())
+    });
+
+    let get = |path: &str| router.handle_request(Request::get(path));
+
+    assert_eq!(get("/users/42").body_text(), "user 42");
+    assert_eq!(get("/users/42?expand=true").body_text(), "user 42");
+    assert_eq!(get("/users/me").body_text(), "me");
+    assert_eq!(get("/users/jane-doe").body_text(), "slug jane-doe");
+    assert_eq!(get("/users/Jane%20Doe").status_code, 404);
+    assert_eq!(get("/users/-1").status_code, 400);
+    assert_eq!(
+        get("/items/123E4567-E89B-12D3-A456-426614174000").body_text(),
+        "123e4567-e89b-12d3-a456-426614174000"
+    );
+    assert_eq!(get("/items/not-a-uuid").status_code, 404);
+    assert_eq!(get("/codes/ABC").body_text(), "ABC");
+    assert_eq!(get("/codes/ABCD").status_code, 404);
+    assert_eq!(
+        router.handle_request(Request::post("/users/42")).status_code,
+        405
+    );
+}
+
+#[test]
+#[should_panic(expected = "Route conflict: GET /users/{user_id:int} is already registered as /users/{id:int}")]
+fn test_equivalent_patterns_conflict() {
+    let mut router = Router::new();
+    router.get("/users/{id:int}", ok);
+    router.get("/users/{user_id:int}", ok);
+}
+
+#[test]
+fn test_route_timeout_override() {
+    let mut api = Router::new();
+    api.get("/export", |_| Response::new(200)).timeout(Duration::from_secs(90));
+
+    let mut router = Router::new();
+    router.get("/", |_| Response::new(200));
+    router.post("/reports/{id:int}", |_| Response::new(200)).timeout(Duration::from_secs(120));
+    router.host("api.example.com", api);
+
+    assert_eq!(router.route_timeout(&Request::get("/")), None);
+    assert_eq!(router.route_timeout(&Request::post("/reports/7")), Some(Duration::from_secs(120)));
+    assert_eq!(router.route_timeout(&Request::post("/reports/x")), None);
+    assert_eq!(
+        router.route_timeout(&Request::get("/export").with_header("Host", "api.example.com")),
+        Some(Duration::from_secs(90))
+    );
+}
+
+#[test]
+fn test_route_max_body_size_override() {
+    let mut router = Router::new();
+    router.post("/notes", |_| Response::new(200));
+    router.post("/uploads", |_| Response::new(200)).max_body_size(1 << 30);
+
+    assert_eq!(router.route_max_body_size(&Request::post("/notes")), None);
+    assert_eq!(router.route_max_body_size(&Request::post("/uploads")), Some(1 << 30));
+    assert_eq!(router.route_max_body_size(&Request::get("/uploads")), None);
+}
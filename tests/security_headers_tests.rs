@@ -0,0 +1,114 @@
+use rust_http_server::middleware::{CspNonce, SecurityHeaders};
+use rust_http_server::{Request, Response, Router, TlsInfo};
+use std::time::Duration;
+
+fn router_with(headers: SecurityHeaders) -> Router {
+    let mut router = Router::new();
+    router.use_global(headers);
+    router.get("/", |req: Request| {
+        let nonce = req.extension::<CspNonce>().map(|n| n.as_str().to_string()).unwrap_or_default();
+        Response::new(200).with_body(&nonce)
+    });
+    router.get("/embeddable", |_| Response::new(200).with_header("X-Frame-Options", "SAMEORIGIN"));
+    router
+}
+
+fn https(mut req: Request) -> Request {
+    req.tls = Some(TlsInfo {
+        version: "TLSv1.3".to_string(),
+        cipher_suite: "TLS13_AES_128_GCM_SHA256".to_string(),
+        alpn_protocol: None,
+        server_name: None,
+    });
+    req
+}
+
+#[test]
+fn test_default_headers() {
+    let router = router_with(SecurityHeaders::new());
+
+    let response = router.handle_request(Request::get("/"));
+    assert_eq!(response.headers.get("X-Content-Type-Options"), Some("nosniff"));
+    assert_eq!(response.headers.get("X-Frame-Options"), Some("DENY"));
+    assert_eq!(response.headers.get("Referrer-Policy"), Some("strict-origin-when-cross-origin"));
+    assert_eq!(response.headers.get("Cross-Origin-Opener-Policy"), Some("same-origin"));
+    assert_eq!(response.headers.get("Cross-Origin-Resource-Policy"), Some("same-origin"));
+    assert_eq!(response.headers.get("Content-Security-Policy"), None);
+    // HSTS only over TLS.
+    assert_eq!(response.headers.get("Strict-Transport-Security"), None);
+
+    let response = router.handle_request(https(Request::get("/")));
+    assert_eq!(response.headers.get("Strict-Transport-Security"), Some("max-age=31536000; includeSubDomains"));
+
+    let response = router.handle_request(Request::get("/embeddable"));
+    assert_eq!(response.headers.get_all("X-Frame-Options").collect::<Vec<_>>(), vec!["SAMEORIGIN"]);
+}
+
+#[test]
+fn test_builders_and_without() {
+    let router = router_with(
+        SecurityHeaders::new()
+            .hsts(Duration::from_secs(63072000), true, true)
+            .permissions_policy("camera=(), geolocation=()")
+            .cross_origin_embedder_policy("require-corp")
+            .referrer_policy("no-referrer")
+            .without("X-Frame-Options")
+            .without("Strict-Transport-Security"),
+    );
+
+    let response = router.handle_request(https(Request::get("/")));
+    assert_eq!(response.headers.get("Strict-Transport-Security"), None);
+    assert_eq!(response.headers.get("X-Frame-Options"), None);
+    assert_eq!(response.headers.get("Permissions-Policy"), Some("camera=(), geolocation=()"));
+    assert_eq!(response.headers.get("Cross-Origin-Embedder-Policy"), Some("require-corp"));
+    assert_eq!(response.headers.get("Referrer-Policy"), Some("no-referrer"));
+
+    let router = router_with(SecurityHeaders::new().hsts(Duration::from_secs(600), false, true));
+    let response = router.handle_request(https(Request::get("/")));
+    assert_eq!(response.headers.get("Strict-Transport-Security"), Some("max-age=600; preload"));
+}
+
+#[test]
+fn test_without_switches_off_csp_under_either_name() {
+    for name in ["Content-Security-Policy", "Content-Security-Policy-Report-Only"] {
+        let router = router_with(
+            SecurityHeaders::new()
+                .content_security_policy("default-src 'self'")
+                .csp_report_only(true)
+                .without(name),
+        );
+        let response = router.handle_request(Request::get("/"));
+        assert_eq!(response.headers.get("Content-Security-Policy"), None, "{}", name);
+        assert_eq!(response.headers.get("Content-Security-Policy-Report-Only"), None, "{}", name);
+    }
+}
+
+#[test]
+fn test_csp_nonce_per_request() {
+    let router = router_with(
+        SecurityHeaders::new().content_security_policy("default-src 'self'; script-src 'nonce-{nonce}'"),
+    );
+
+    let first = router.handle_request(Request::get("/"));
+    let nonce = first.body_text().into_owned();
+    assert_eq!(nonce.len(), 24);
+    assert_eq!(
+        first.headers.get("Content-Security-Policy").unwrap(),
+        format!("default-src 'self'; script-src 'nonce-{}'", nonce)
+    );
+
+    let second = router.handle_request(Request::get("/"));
+    assert_ne!(second.body_text(), nonce);
+
+    let router = router_with(SecurityHeaders::new().content_security_policy("default-src 'self'").csp_report_only(true));
+    let response = router.handle_request(Request::get("/"));
+    assert_eq!(response.headers.get("Content-Security-Policy"), None);
+    assert_eq!(response.headers.get("Content-Security-Policy-Report-Only"), Some("default-src 'self'"));
+    assert_eq!(response.body_text(), "");
+}
+
+#[test]
+#[should_panic(expected = "Invalid middleware configuration")]
+fn test_invalid_header_value_panics_at_configuration() {
+    SecurityHeaders::new().content_security_policy("default-src 'self'\r\nSet-Cookie: a=b");
+}
@@ -0,0 +1,212 @@
+use rust_http_server::middleware::session::SessionData;
+use rust_http_server::middleware::{FileStore, MemoryStore, Session, SessionStore, Sessions};
+use rust_http_server::{Request, Response, Router};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn app(store: Arc<dyn SessionStore>) -> Router {
+    let mut router = Router::new();
+    router.use_global(Sessions::with_store(store).cookie_name("sid").secure(false));
+    router.get("/visit", |req: Request| {
+        let session = req.extension::<Session>().unwrap();
+        let visits: u32 = session.get("visits").unwrap_or(0) + 1;
+        session.insert("visits", visits);
+        Response::new(200).with_body(&visits.to_string())
+    });
+    router.get("/peek", |req: Request| {
+        let session = req.extension::<Session>().unwrap();
+        Response::new(200).with_body(&session.get::<String>("user").unwrap_or_default())
+    });
+    router.post("/login", |req: Request| {
+        let session = req.extension::<Session>().unwrap();
+        session.renew();
+        session.insert("user", "alice");
+        Response::new(204)
+    });
+    router.post("/logout", |req: Request| {
+        req.extension::<Session>().unwrap().destroy();
+        Response::new(204)
+    });
+    router
+}
+
+fn session_id(response: &Response) -> Option<String> {
+    let header = response.headers.get("Set-Cookie")?;
+    let (pair, _) = header.split_once(';')?;
+    pair.strip_prefix("sid=").map(str::to_string)
+}
+
+fn with_session(req: Request, id: &str) -> Request {
+    req.with_header("Cookie", &format!("sid={}", id))
+}
+
+#[test]
+fn test_session_cookie_issued_on_first_write() {
+    let store = Arc::new(MemoryStore::new());
+    let router = app(store.clone());
+
+    let response = router.handle_request(Request::get("/peek"));
+    assert_eq!(response.headers.get("Set-Cookie"), None);
+    assert!(store.is_empty());
+
+    let response = router.handle_request(Request::get("/visit"));
+    let cookie = response.headers.get("Set-Cookie").unwrap();
+    assert!(cookie.contains("; Path=/; HttpOnly; SameSite=Lax"), "{}", cookie);
+    let id = session_id(&response).unwrap();
+    assert_eq!(id.len(), 43);
+
+    let response = router.handle_request(with_session(Request::get("/visit"), &id));
+    assert_eq!(response.body_text(), "2");
+    assert_eq!(response.headers.get("Set-Cookie"), None);
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn test_unknown_session_id_is_not_adopted() {
+    let router = app(Arc::new(MemoryStore::new()));
+    let forged = "A".repeat(43);
+
+    let response = router.handle_request(with_session(Request::get("/visit"), &forged));
+    assert_eq!(response.body_text(), "1");
+    let id = session_id(&response).unwrap();
+    assert_ne!(id, forged);
+}
+
+#[test]
+fn test_renew_rotates_id_and_destroy_clears() {
+    let store = Arc::new(MemoryStore::new());
+    let router = app(store.clone());
+
+    let first = session_id(&router.handle_request(Request::get("/visit"))).unwrap();
+    let response = router.handle_request(with_session(Request::post("/login"), &first));
+    let second = session_id(&response).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(store.load(&first).unwrap(), None);
+
+    let response = router.handle_request(with_session(Request::get("/visit"), &second));
+    assert_eq!(response.body_text(), "2");
+    let response = router.handle_request(with_session(Request::get("/peek"), &second));
+    assert_eq!(response.body_text(), "alice");
+
+    let response = router.handle_request(with_session(Request::post("/logout"), &second));
+    assert!(response.headers.get("Set-Cookie").unwrap().starts_with("sid=; Path=/; Expires="));
+    assert!(store.is_empty());
+}
+
+#[test]
+fn test_memory_store_ttl_and_cleanup() {
+    let store = MemoryStore::new();
+    let data = SessionData::from([("k".to_string(), "v".to_string())]);
+    store.save("live", &data, Duration::from_secs(60)).unwrap();
+    store.save("stale", &data, Duration::ZERO).unwrap();
+
+    assert_eq!(store.load("live").unwrap(), Some(data));
+    assert_eq!(store.load("stale").unwrap(), None);
+    assert_eq!(store.len(), 2);
+    store.cleanup().unwrap();
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn test_file_store_round_trip() {
+    let dir = std::env::temp_dir().join(format!("session-test-{}", std::process::id()));
+    let store = FileStore::new(&dir).unwrap();
+    let data = SessionData::from([
+        ("user".to_string(), "alice".to_string()),
+        ("note".to_string(), "a=b%c\nline two".to_string()),
+    ]);
+
+    store.save("abc", &data, Duration::from_secs(60)).unwrap();
+    assert_eq!(store.load("abc").unwrap(), Some(data.clone()));
+
+    store.save("old", &data, Duration::ZERO).unwrap();
+    assert_eq!(store.load("old").unwrap(), None);
+    store.cleanup().unwrap();
+    assert!(!dir.join("old.session").exists());
+    assert!(dir.join("abc.session").exists());
+
+    store.delete("abc").unwrap();
+    store.delete("abc").unwrap();
+    assert_eq!(store.load("abc").unwrap(), None);
+
+    let router = app(Arc::new(store));
+    let id = session_id(&router.handle_request(Request::get("/visit"))).unwrap();
+    let response = router.handle_request(with_session(Request::get("/visit"), &id));
+    assert_eq!(response.body_text(), "2");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[derive(Default)]
+struct CountingStore {
+    inner: MemoryStore,
+    saves: std::sync::atomic::AtomicUsize,
+    touches: std::sync::atomic::AtomicUsize,
+}
+
+impl SessionStore for CountingStore {
+    fn load(&self, id: &str) -> std::io::Result<Option<SessionData>> {
+        self.inner.load(id)
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> std::io::Result<()> {
+        self.saves.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.inner.save(id, data, ttl)
+    }
+
+    fn delete(&self, id: &str) -> std::io::Result<()> {
+        self.inner.delete(id)
+    }
+
+    fn touch(&self, id: &str, data: &SessionData, ttl: Duration) -> std::io::Result<()> {
+        self.touches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.inner.touch(id, data, ttl)
+    }
+}
+
+#[test]
+fn test_unchanged_sessions_are_only_touched() {
+    use std::sync::atomic::Ordering;
+
+    let store = Arc::new(CountingStore::default());
+    let router = app(store.clone());
+    let id = session_id(&router.handle_request(Request::get("/visit"))).unwrap();
+    assert_eq!(store.saves.load(Ordering::SeqCst), 1);
+
+    router.handle_request(with_session(Request::get("/peek"), &id));
+    router.handle_request(with_session(Request::get("/peek"), &id));
+    assert_eq!(store.saves.load(Ordering::SeqCst), 1);
+    assert_eq!(store.touches.load(Ordering::SeqCst), 2);
+
+    router.handle_request(with_session(Request::get("/visit"), &id));
+    assert_eq!(store.saves.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_file_store_touch_skips_fresh_sessions() {
+    let dir = std::env::temp_dir().join(format!("session-touch-test-{}", std::process::id()));
+    let store = FileStore::new(&dir).unwrap();
+    let data = SessionData::from([("user".to_string(), "alice".to_string())]);
+    let path = dir.join("abc.session");
+
+    store.save("abc", &data, Duration::from_secs(3600)).unwrap();
+    std::fs::write(&path, format!("{}\nuser=alice\n", u64::MAX / 2)).unwrap();
+    store.touch("abc", &data, Duration::from_secs(3600)).unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().starts_with(&(u64::MAX / 2).to_string()));
+
+    // Close to expiry, the file is rewritten.
+    let soon = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 60;
+    std::fs::write(&path, format!("{}\nuser=alice\n", soon)).unwrap();
+    store.touch("abc", &data, Duration::from_secs(3600)).unwrap();
+    assert_ne!(std::fs::read_to_string(&path).unwrap().lines().next(), Some(soon.to_string().as_str()));
+    assert_eq!(store.load("abc").unwrap(), Some(data));
+
+    // Only the session file is left behind.
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(files, vec![std::ffi::OsString::from("abc.session")]);
+    std::fs::remove_dir_all(dir).unwrap();
+}